@@ -1,6 +1,11 @@
 use crate::character::Character;
+use crate::config::GameConfig;
+use crate::ruleset::ActionKind;
 
-#[derive(Debug, Clone, PartialEq)]
+/// Coins a player may Give a teammate in one turn.
+pub const GIFTS: [usize; 3] = [1, 2, 3];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Action {
     Income,
     ForeignAid,
@@ -9,19 +14,36 @@ pub enum Action {
     Assassinate(usize),
     Steal(usize, usize), //target, coins
     Exchange,
-    Block(BlockAction, usize, Option<Character>), //BlockAction, player to be blocked, character
+    /// Claims the character to block with, truthfully or not.
+    Block(BlockAction, usize, Character), //BlockAction, player to be blocked, claimed character
     Challenge(ChallengeAction, usize),
-    Pass
+    Pass,
+    /// Reformation: switch the target's faction, paying the Treasury Reserve.
+    Convert(usize), //target, possibly oneself
+    /// Reformation: take the Treasury Reserve, claiming not to hold a Duke.
+    Embezzle,
+    /// Inquisitor: look at one of the target's cards.
+    Examine(usize), //target
+    /// Inquisitor: after examining, make the target swap the card they showed.
+    ForceExchange(usize), //target
+    /// Team play: hand coins to a teammate.
+    Give(usize, usize), //teammate, coins
+    /// Socialist: take a coin from every richer opponent.
+    Redistribute,
+    /// Speculator: gain as many coins as you have, up to the Tax amount.
+    Speculate,
+    /// Reporter: take Income and exchange one card.
+    Report,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BlockAction {
     Assassinate(usize), //from
     Stealing(usize, usize), //from
     ForeignAid(usize), //from
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ChallengeAction {
     BlockAssassination(usize),
     BlockStealing(usize, usize),
@@ -30,6 +52,42 @@ pub enum ChallengeAction {
     Stealing(usize, usize),
     Assassination(usize),
     Tax,
+    Embezzle,
+    Examine(usize),
+    Redistribute,
+    Speculate,
+    Report,
+}
+
+impl BlockAction {
+    /// The same block with every seat index passed through `f`.
+    pub fn map_seats<F: Fn(usize) -> usize>(&self, f: F) -> BlockAction {
+        match self {
+            BlockAction::Assassinate(from) => BlockAction::Assassinate(f(*from)),
+            BlockAction::Stealing(from, coins) => BlockAction::Stealing(f(*from), *coins),
+            BlockAction::ForeignAid(from) => BlockAction::ForeignAid(f(*from)),
+        }
+    }
+}
+
+impl ChallengeAction {
+    /// The same challenge with every seat index passed through `f`.
+    pub fn map_seats<F: Fn(usize) -> usize>(&self, f: F) -> ChallengeAction {
+        match self {
+            ChallengeAction::BlockAssassination(from) => ChallengeAction::BlockAssassination(f(*from)),
+            ChallengeAction::BlockStealing(from, coins) => ChallengeAction::BlockStealing(f(*from), *coins),
+            ChallengeAction::BlockForeignAid(from) => ChallengeAction::BlockForeignAid(f(*from)),
+            ChallengeAction::Stealing(target, coins) => ChallengeAction::Stealing(f(*target), *coins),
+            ChallengeAction::Assassination(target) => ChallengeAction::Assassination(f(*target)),
+            ChallengeAction::Exchange => ChallengeAction::Exchange,
+            ChallengeAction::Tax => ChallengeAction::Tax,
+            ChallengeAction::Embezzle => ChallengeAction::Embezzle,
+            ChallengeAction::Examine(target) => ChallengeAction::Examine(f(*target)),
+            ChallengeAction::Redistribute => ChallengeAction::Redistribute,
+            ChallengeAction::Speculate => ChallengeAction::Speculate,
+            ChallengeAction::Report => ChallengeAction::Report,
+        }
+    }
 }
 
 impl std::fmt::Display for ChallengeAction {
@@ -42,6 +100,11 @@ impl std::fmt::Display for ChallengeAction {
             ChallengeAction::Stealing(target, coins) => write!(f, "Stealing on player {} with {} coins", target, coins),
             ChallengeAction::Assassination(target) => write!(f, "Assassination on player {}", target),
             ChallengeAction::Tax => write!(f, "Tax"),
+            ChallengeAction::Embezzle => write!(f, "Embezzle"),
+            ChallengeAction::Examine(target) => write!(f, "Examine on player {}", target),
+            ChallengeAction::Redistribute => write!(f, "Redistribute"),
+            ChallengeAction::Speculate => write!(f, "Speculate"),
+            ChallengeAction::Report => write!(f, "Report"),
         }
     }
 }
@@ -66,9 +129,17 @@ impl std::fmt::Display for Action {
             Action::Assassinate(target) => write!(f, "Assassinate player {}", target),
             Action::Steal(target, _) => write!(f, "Steal from player {}", target),
             Action::Exchange => write!(f, "Exchange"),
-            Action::Block(block_action, target, _char) => write!(f, "Block {} on player {}.", block_action, target),
+            Action::Block(block_action, target, character) => write!(f, "Block {} on player {} as {}.", block_action, target, character),
             Action::Challenge(challenge_action, target) => write!(f, "Challenge {} on player {}", challenge_action, target),
             Action::Pass => write!(f, "Pass"),
+            Action::Convert(target) => write!(f, "Convert player {}", target),
+            Action::Embezzle => write!(f, "Embezzle"),
+            Action::Examine(target) => write!(f, "Examine player {}", target),
+            Action::ForceExchange(target) => write!(f, "Force player {} to exchange", target),
+            Action::Give(target, coins) => write!(f, "Give {} coins to player {}", coins, target),
+            Action::Redistribute => write!(f, "Redistribute"),
+            Action::Speculate => write!(f, "Speculate"),
+            Action::Report => write!(f, "Report"),
         }
     }
 }
@@ -86,6 +157,42 @@ impl Action {
             Action::Block(_, _, _) => false,
             Action::Challenge(_, _) => false,
             Action::Pass => false,
+            Action::Convert(_) => true,
+            Action::Embezzle => true,
+            Action::Examine(_) => true,
+            Action::ForceExchange(_) => false,
+            Action::Give(_, _) => true,
+            Action::Redistribute => true,
+            Action::Speculate => true,
+            Action::Report => true,
+        }
+    }
+
+    /// The same action with every seat index passed through `f`, e.g. to express it
+    /// relative to the deciding player.
+    pub fn map_seats<F: Fn(usize) -> usize>(&self, f: F) -> Action {
+        match self {
+            Action::Coup(target) => Action::Coup(f(*target)),
+            Action::Assassinate(target) => Action::Assassinate(f(*target)),
+            Action::Steal(target, coins) => Action::Steal(f(*target), *coins),
+            Action::Convert(target) => Action::Convert(f(*target)),
+            Action::Examine(target) => Action::Examine(f(*target)),
+            Action::ForceExchange(target) => Action::ForceExchange(f(*target)),
+            Action::Give(target, coins) => Action::Give(f(*target), *coins),
+            Action::Block(block_action, player, character) => Action::Block(block_action.map_seats(&f), f(*player), *character),
+            Action::Challenge(challenge_action, player) => Action::Challenge(challenge_action.map_seats(&f), f(*player)),
+            _ => self.clone(),
+        }
+    }
+
+    /// The player an action is aimed at, if any.
+    pub fn target(&self) -> Option<usize> {
+        match self {
+            Action::Coup(target) => Some(*target),
+            Action::Assassinate(target) => Some(*target),
+            Action::Steal(target, _) => Some(*target),
+            Action::Examine(target) => Some(*target),
+            _ => None,
         }
     }
 
@@ -101,91 +208,130 @@ impl Action {
             Action::Block(_, _, _) => true,
             Action::Challenge(_, _) => false,
             Action::Pass => false,
+            Action::Convert(_) => true,
+            Action::Embezzle => true,
+            Action::Examine(_) => true,
+            Action::ForceExchange(_) => false,
+            Action::Give(_, _) => true,
+            Action::Redistribute => true,
+            Action::Speculate => true,
+            Action::Report => true,
         }
     }
 
+    /// Every decision the player in `seats[0]` can make when a Steal takes at most
+    /// `steal` coins, where the rest of `seats` are the other seats clockwise. The list
+    /// has the same length for every table of `seats.len()` players, and for 3 players
+    /// and the official Steal it is [`Action::all_3players`].
+    pub fn all_players(seats: &[usize], steal: usize) -> Vec<Action> {
+        let me = seats[0];
+        let others = &seats[1..];
+        // Pairs of other seats, used when a third party challenges.
+        let pairs: Vec<(usize, usize)> = others.iter().flat_map(|&a| others.iter().filter(move |&&b| b != a).map(move |&b| (a, b))).collect();
+        let amounts = steal_amounts(steal);
+        let mut actions = vec![Action::Income, Action::ForeignAid, Action::Tax];
+
+        actions.extend(others.iter().map(|&o| Action::Coup(o)));
+        actions.extend(others.iter().map(|&o| Action::Assassinate(o)));
+        for &amount in &amounts {
+            actions.extend(others.iter().map(|&o| Action::Steal(o, amount)));
+        }
+        actions.push(Action::Exchange);
+
+        for (kind, character) in [(ActionKind::Assassinate, Character::Contessa), (ActionKind::Steal, Character::Captain), (ActionKind::Steal, Character::Ambassador), (ActionKind::ForeignAid, Character::Duke)] {
+            actions.extend(Action::block_players(seats, steal, kind, character));
+        }
+
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::BlockForeignAid(me), o)));
+        actions.extend(pairs.iter().map(|&(a, b)| Action::Challenge(ChallengeAction::BlockForeignAid(a), b)));
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::BlockAssassination(o), o)));
+        for &amount in &amounts {
+            actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::BlockStealing(o, amount), o)));
+        }
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::Exchange, o)));
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::Tax, o)));
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::Assassination(me), o)));
+        actions.extend(pairs.iter().map(|&(a, b)| Action::Challenge(ChallengeAction::Assassination(a), b)));
+        for &amount in &amounts {
+            actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::Stealing(me, amount), o)));
+            actions.extend(pairs.iter().map(|&(a, b)| Action::Challenge(ChallengeAction::Stealing(a, amount), b)));
+        }
+        actions.push(Action::Pass);
+        actions
+    }
+
+    /// The decisions the Reformation expansion adds to [`Action::all_players`] for the
+    /// player in `seats[0]`: conversions, Embezzle and challenging it.
+    pub fn reformation_players(seats: &[usize]) -> Vec<Action> {
+        let others = &seats[1..];
+        let mut actions: Vec<Action> = seats.iter().map(|&s| Action::Convert(s)).collect();
+        actions.push(Action::Embezzle);
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::Embezzle, o)));
+        actions
+    }
+
+    /// The decisions an Inquisitor game adds to [`Action::all_players`] for the player in
+    /// `seats[0]`: examining, forcing an exchange, blocking stealing as the Inquisitor
+    /// and challenging an examination.
+    pub fn inquisitor_players(seats: &[usize], steal: usize) -> Vec<Action> {
+        let me = seats[0];
+        let others = &seats[1..];
+        let pairs: Vec<(usize, usize)> = others.iter().flat_map(|&a| others.iter().filter(move |&&b| b != a).map(move |&b| (a, b))).collect();
+        let mut actions: Vec<Action> = others.iter().map(|&o| Action::Examine(o)).collect();
+        actions.extend(others.iter().map(|&o| Action::ForceExchange(o)));
+        actions.extend(Action::block_players(seats, steal, ActionKind::Steal, Character::Inquisitor));
+        actions.extend(others.iter().map(|&o| Action::Challenge(ChallengeAction::Examine(me), o)));
+        actions.extend(pairs.iter().map(|&(a, b)| Action::Challenge(ChallengeAction::Examine(a), b)));
+        actions
+    }
+
+    /// Every block of `kind` the player in `seats[0]` can raise claiming `character`.
+    /// Empty for actions that cannot be blocked.
+    pub fn block_players(seats: &[usize], steal: usize, kind: ActionKind, character: Character) -> Vec<Action> {
+        let me = seats[0];
+        let others = &seats[1..];
+        match kind {
+            ActionKind::Assassinate => others.iter().map(|&o| Action::Block(BlockAction::Assassinate(me), o, character)).collect(),
+            ActionKind::Steal => steal_amounts(steal).into_iter()
+                .flat_map(|amount| others.iter().map(move |&o| Action::Block(BlockAction::Stealing(me, amount), o, character)))
+                .collect(),
+            ActionKind::ForeignAid => others.iter().map(|&o| Action::Block(BlockAction::ForeignAid(o), o, character)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The gifts team play adds to [`Action::all_players`] for the player in `seats[0]`.
+    pub fn team_players(seats: &[usize]) -> Vec<Action> {
+        let others = &seats[1..];
+        let mut actions = Vec::new();
+        for coins in GIFTS {
+            actions.extend(others.iter().map(|&o| Action::Give(o, coins)));
+        }
+        actions
+    }
+
+    /// The decisions the Anarchy pack adds to [`Action::all_players`] for the player in
+    /// `seats[0]`: the new roles' actions, their blocks and challenging their actions.
+    pub fn anarchy_players(seats: &[usize], steal: usize) -> Vec<Action> {
+        let others = &seats[1..];
+        let mut actions = vec![Action::Redistribute, Action::Speculate, Action::Report];
+        actions.extend(Action::block_players(seats, steal, ActionKind::Steal, Character::Reporter));
+        actions.extend(Action::block_players(seats, steal, ActionKind::ForeignAid, Character::Socialist));
+        for challenge in [ChallengeAction::Redistribute, ChallengeAction::Speculate, ChallengeAction::Report] {
+            actions.extend(others.iter().map(|&o| Action::Challenge(challenge, o)));
+        }
+        actions
+    }
+
+    /// Every decision a player in seat `pos0` can make in a 3-player game, where `pos1`
+    /// and `pos2` are the next seats clockwise. Blocks and challenges are written the
+    /// way the engine builds them in `Player::possible_actions`.
     pub fn all_3players(pos0: usize, pos1: usize, pos2:usize) -> Vec<Action> {
-        vec![
-            Action::Income,
-            Action::ForeignAid,
-            Action::Tax,
-            Action::Coup(pos1),
-            Action::Coup(pos2),
-            Action::Assassinate(pos1),
-            Action::Assassinate(pos2),
-            Action::Steal(pos1, 2),
-            Action::Steal(pos2, 2),
-            Action::Steal(pos1, 1),
-            Action::Steal(pos2, 1),
-            Action::Steal(pos1, 0),
-            Action::Steal(pos2, 0),
-            Action::Exchange,
-            Action::Block(BlockAction::Assassinate(pos1), pos0, None),
-            Action::Block(BlockAction::Assassinate(pos2), pos0, None),
-            Action::Block(BlockAction::Stealing(pos1, 2), pos0, None),
-            Action::Block(BlockAction::Stealing(pos2, 2), pos0, None),
-            Action::Block(BlockAction::Stealing(pos1, 1), pos0, None),
-            Action::Block(BlockAction::Stealing(pos2, 1), pos0, None),
-            Action::Block(BlockAction::Stealing(pos1, 0), pos0, None),
-            Action::Block(BlockAction::Stealing(pos2, 0), pos0, None),
-            Action::Block(BlockAction::ForeignAid(pos1), pos0, None),
-            Action::Block(BlockAction::ForeignAid(pos2), pos0, None),
-            Action::Block(BlockAction::Assassinate(pos1), pos0, Some(Character::Contessa)),
-            Action::Block(BlockAction::Assassinate(pos2), pos0, Some(Character::Contessa)),
-            Action::Block(BlockAction::Stealing(pos1, 2), pos0, Some(Character::Captain)),
-            Action::Block(BlockAction::Stealing(pos2, 2), pos0, Some(Character::Captain)),
-            Action::Block(BlockAction::Stealing(pos1, 1), pos0, Some(Character::Captain)),
-            Action::Block(BlockAction::Stealing(pos2, 1), pos0, Some(Character::Captain)),
-            Action::Block(BlockAction::Stealing(pos1, 0), pos0, Some(Character::Captain)),
-            Action::Block(BlockAction::Stealing(pos2, 0), pos0, Some(Character::Captain)),
-            Action::Block(BlockAction::Stealing(pos1, 2), pos0, Some(Character::Ambassador)),
-            Action::Block(BlockAction::Stealing(pos2, 2), pos0, Some(Character::Ambassador)),
-            Action::Block(BlockAction::Stealing(pos1, 1), pos0, Some(Character::Ambassador)),
-            Action::Block(BlockAction::Stealing(pos2, 1), pos0, Some(Character::Ambassador)),
-            Action::Block(BlockAction::Stealing(pos1, 0), pos0, Some(Character::Ambassador)),
-            Action::Block(BlockAction::Stealing(pos2, 0), pos0, Some(Character::Ambassador)),
-            Action::Block(BlockAction::ForeignAid(pos1), pos0, Some(Character::Duke)),
-            Action::Block(BlockAction::ForeignAid(pos2), pos0, Some(Character::Duke)),
-            Action::Challenge(ChallengeAction::BlockForeignAid(pos0), pos1),
-            Action::Challenge(ChallengeAction::BlockForeignAid(pos0), pos2),
-            Action::Challenge(ChallengeAction::BlockForeignAid(pos1), pos2),
-            Action::Challenge(ChallengeAction::BlockForeignAid(pos2), pos1),
-            Action::Challenge(ChallengeAction::BlockAssassination(pos0), pos1),
-            Action::Challenge(ChallengeAction::BlockAssassination(pos0), pos2),
-            Action::Challenge(ChallengeAction::BlockAssassination(pos1), pos2),
-            Action::Challenge(ChallengeAction::BlockAssassination(pos2), pos1),
-            Action::Challenge(ChallengeAction::BlockStealing(pos0, 2), pos1),
-            Action::Challenge(ChallengeAction::BlockStealing(pos0, 2), pos2),
-            Action::Challenge(ChallengeAction::BlockStealing(pos1, 2), pos2),
-            Action::Challenge(ChallengeAction::BlockStealing(pos2, 2), pos1),
-            Action::Challenge(ChallengeAction::BlockStealing(pos0, 1), pos1),
-            Action::Challenge(ChallengeAction::BlockStealing(pos0, 1), pos2),
-            Action::Challenge(ChallengeAction::BlockStealing(pos1, 1), pos2),
-            Action::Challenge(ChallengeAction::BlockStealing(pos2, 1), pos1),
-            Action::Challenge(ChallengeAction::BlockStealing(pos0, 0), pos1),
-            Action::Challenge(ChallengeAction::BlockStealing(pos0, 0), pos2),
-            Action::Challenge(ChallengeAction::BlockStealing(pos1, 0), pos2),
-            Action::Challenge(ChallengeAction::BlockStealing(pos2, 0), pos1),
-            Action::Challenge(ChallengeAction::Exchange, pos1),
-            Action::Challenge(ChallengeAction::Exchange, pos2),
-            Action::Challenge(ChallengeAction::Tax, pos1),
-            Action::Challenge(ChallengeAction::Tax, pos2),
-            Action::Challenge(ChallengeAction::Assassination(pos0), pos1),
-            Action::Challenge(ChallengeAction::Assassination(pos0), pos2),
-            Action::Challenge(ChallengeAction::Assassination(pos1), pos2),
-            Action::Challenge(ChallengeAction::Assassination(pos2), pos1),
-            Action::Challenge(ChallengeAction::Stealing(pos0, 2), pos1),
-            Action::Challenge(ChallengeAction::Stealing(pos0, 2), pos2),
-            Action::Challenge(ChallengeAction::Stealing(pos1, 2), pos2),
-            Action::Challenge(ChallengeAction::Stealing(pos2, 2), pos1),
-            Action::Challenge(ChallengeAction::Stealing(pos0, 1), pos1),
-            Action::Challenge(ChallengeAction::Stealing(pos0, 1), pos2),
-            Action::Challenge(ChallengeAction::Stealing(pos1, 1), pos2),
-            Action::Challenge(ChallengeAction::Stealing(pos2, 1), pos1),
-            Action::Challenge(ChallengeAction::Stealing(pos0, 0), pos1),
-            Action::Challenge(ChallengeAction::Stealing(pos0, 0), pos2),
-            Action::Challenge(ChallengeAction::Stealing(pos1, 0), pos2),
-            Action::Challenge(ChallengeAction::Stealing(pos2, 0), pos1),
-        ]
-    }
-}
\ No newline at end of file
+        Action::all_players(&[pos0, pos1, pos2], GameConfig::default().steal)
+    }
+}
+
+/// Coins a Steal can take when it takes at most `steal`, largest first.
+fn steal_amounts(steal: usize) -> Vec<usize> {
+    (0..=steal).rev().collect()
+}
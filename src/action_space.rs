@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use crate::action::Action;
+use crate::game::GameState;
+use crate::pack::CharacterPack;
+use crate::config::GameConfig;
+use crate::ruleset::ActionKind;
+
+/// Numbers every decision of an `num_players`-player game densely from 0.
+///
+/// Seats are stored relative to the deciding player, 0 being that player and 1 the next
+/// seat clockwise, so an index means the same move whichever seat takes it. The order
+/// is [`Action::all_players`], which for 3 players and the official Steal matches
+/// [`Action::all_3players`].
+#[derive(Debug, Clone)]
+pub struct ActionSpace {
+    num_players: usize,
+    actions: Vec<Action>,
+    index: HashMap<Action, usize>,
+}
+
+impl ActionSpace {
+    /// The space of a base game with the official Steal.
+    pub fn new(num_players: usize) -> ActionSpace {
+        let seats: Vec<usize> = (0..num_players).collect();
+        ActionSpace::from_actions(num_players, Action::all_players(&seats, GameConfig::default().steal))
+    }
+
+    /// The space of a Reformation game: [`ActionSpace::new`] followed by
+    /// [`Action::reformation_players`], so the base indices keep their meaning.
+    pub fn reformation(num_players: usize) -> ActionSpace {
+        ActionSpace::with_expansions(num_players, GameConfig::default().steal, true, false, false, false)
+    }
+
+    /// The base space for Steals of up to `steal` coins, followed by the decisions of the
+    /// chosen expansions, Reformation first, then the Inquisitor, gifts between
+    /// teammates and the Anarchy pack.
+    pub fn with_expansions(num_players: usize, steal: usize, reformation: bool, inquisitor: bool, giving: bool, anarchy: bool) -> ActionSpace {
+        let seats: Vec<usize> = (0..num_players).collect();
+        let mut actions = Action::all_players(&seats, steal);
+        if reformation {
+            actions.extend(Action::reformation_players(&seats));
+        }
+        if inquisitor {
+            actions.extend(Action::inquisitor_players(&seats, steal));
+        }
+        if giving {
+            actions.extend(Action::team_players(&seats));
+        }
+        if anarchy {
+            actions.extend(Action::anarchy_players(&seats, steal));
+        }
+        ActionSpace::from_actions(num_players, actions)
+    }
+
+    /// The space covering every decision of `game` with its expansions and house rules,
+    /// followed by the blocks of characters that rules with moved powers let block.
+    pub fn for_game(game: &GameState) -> ActionSpace {
+        let anarchy = [ActionKind::Redistribute, ActionKind::Speculate, ActionKind::Report].into_iter().any(|k| game.rules.enables(k))
+            || CharacterPack::anarchy().roles.iter().any(|r| game.rules.characters().contains(&r.character));
+        let steal = game.rules.config.steal;
+        let space = ActionSpace::with_expansions(game.players.len(), steal, game.factions.is_some(), game.rules.enables(ActionKind::Examine), game.teams.as_ref().is_some_and(|t| t.giving), anarchy);
+        let seats: Vec<usize> = (0..game.players.len()).collect();
+        let mut actions = space.actions;
+        for kind in [ActionKind::Assassinate, ActionKind::Steal, ActionKind::ForeignAid] {
+            for character in game.rules.blockers(kind) {
+                for block in Action::block_players(&seats, steal, kind, character) {
+                    if !actions.contains(&block) {
+                        actions.push(block);
+                    }
+                }
+            }
+        }
+        ActionSpace::from_actions(game.players.len(), actions)
+    }
+
+    fn from_actions(num_players: usize, actions: Vec<Action>) -> ActionSpace {
+        let index = actions.iter().enumerate().map(|(i, a)| (a.clone(), i)).collect();
+        ActionSpace { num_players, actions, index }
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Index of `action` taken by the player in seat `player`, or None if the action
+    /// names a seat outside the table or is not a move the engine offers.
+    pub fn encode(&self, action: &Action, player: usize) -> Option<usize> {
+        let n = self.num_players;
+        if player >= n {
+            return None;
+        }
+        let relative = action.map_seats(|seat| (seat + n - player) % n);
+        // Shifting back only restores the action if every seat was on the table.
+        if relative.map_seats(|seat| (seat + player) % n) != *action {
+            return None;
+        }
+        self.index.get(&relative).copied()
+    }
+
+    /// The action `index` stands for when the player in seat `player` takes it.
+    pub fn decode(&self, index: usize, player: usize) -> Option<Action> {
+        let n = self.num_players;
+        if player >= n {
+            return None;
+        }
+        self.actions.get(index).map(|a| a.map_seats(|seat| (seat + player) % n))
+    }
+
+    /// The whole space from seat `player`: entry `i` is what index `i` decodes to.
+    pub fn actions(&self, player: usize) -> Vec<Action> {
+        (0..self.len()).filter_map(|i| self.decode(i, player)).collect()
+    }
+
+    /// Which indices are among `options` for the player in seat `player`.
+    pub fn mask(&self, options: &[Action], player: usize) -> Vec<bool> {
+        let mut legal = vec![false; self.len()];
+        for index in options.iter().filter_map(|a| self.encode(a, player)) {
+            legal[index] = true;
+        }
+        legal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::ActionSpace;
+    use crate::action::{Action, BlockAction, ChallengeAction};
+    use crate::character::Character;
+    use crate::config::GameConfig;
+    use crate::game::{self, GameState};
+    use crate::player::Player;
+    use crate::player::random::RandomPlayer;
+    use crate::ruleset::{ActionKind, Ruleset};
+    use crate::team::Teams;
+
+    #[test]
+    fn every_index_round_trips_from_every_seat() {
+        for n in 2..=6 {
+            for space in [ActionSpace::new(n), ActionSpace::with_expansions(n, 2, true, true, true, true)] {
+                for player in 0..n {
+                    let actions = space.actions(player);
+                    assert_eq!(actions.len(), space.len());
+                    for (i, action) in actions.iter().enumerate() {
+                        assert_eq!(space.encode(action, player), Some(i), "{} players, seat {}: {}", n, player, action);
+                    }
+                    let distinct: HashSet<&Action> = actions.iter().collect();
+                    assert_eq!(distinct.len(), actions.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_claimed_blocker_has_its_own_index() {
+        let space = ActionSpace::with_expansions(4, 2, false, true, false, true);
+        for player in 0..4 {
+            let from = (player + 2) % 4;
+            let indices: Vec<usize> = [Character::Captain, Character::Ambassador, Character::Inquisitor, Character::Reporter].into_iter()
+                .map(|character| {
+                    let block = Action::Block(BlockAction::Stealing(player, 1), from, character);
+                    let index = space.encode(&block, player).unwrap();
+                    assert_eq!(space.decode(index, player), Some(block));
+                    index
+                })
+                .collect();
+            let distinct: HashSet<&usize> = indices.iter().collect();
+            assert_eq!(distinct.len(), indices.len());
+            let duke = space.encode(&Action::Block(BlockAction::ForeignAid(from), from, Character::Duke), player);
+            let socialist = space.encode(&Action::Block(BlockAction::ForeignAid(from), from, Character::Socialist), player);
+            assert!(duke.is_some() && socialist.is_some() && duke != socialist);
+        }
+        assert_eq!(space.encode(&Action::Block(BlockAction::Assassinate(0), 1, Character::Duke), 0), None);
+    }
+
+    #[test]
+    fn moved_blockers_are_encoded_for_the_game() {
+        let mut rules = Ruleset::classic();
+        rules.characters[2].blocks.push(ActionKind::ForeignAid);
+        rules.characters[4].blocks.push(ActionKind::Steal);
+        rules.validate().unwrap();
+        for seed in 0..20 {
+            let players: Vec<Box<dyn Player>> = (0..3).map(|i| Box::new(RandomPlayer::new(&format!("p{}", i), seed * 10 + i as u64)) as Box<dyn Player>).collect();
+            let mut game = GameState::new(players);
+            game.verbose = false;
+            game.rng = StdRng::seed_from_u64(seed);
+            game.decisions = Some(Vec::new());
+            game.set_rules(rules.clone());
+            game::deal(&mut game);
+            let space = ActionSpace::for_game(&game);
+            assert_eq!(space.actions(0)[..ActionSpace::new(3).len()], ActionSpace::new(3).actions(0)[..]);
+            assert!(space.encode(&Action::Block(BlockAction::ForeignAid(1), 1, Character::Captain), 0).is_some());
+            assert!(space.encode(&Action::Block(BlockAction::Stealing(0, 2), 1, Character::Contessa), 0).is_some());
+            game.run();
+            for decision in game.decisions.take().unwrap() {
+                for option in decision.options.iter() {
+                    let index = space.encode(option, decision.player).unwrap_or_else(|| panic!("{} has no index", option));
+                    assert_eq!(space.decode(index, decision.player).as_ref(), Some(option));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn indices_do_not_depend_on_the_seat() {
+        let space = ActionSpace::new(4);
+        assert_eq!(space.encode(&Action::Coup(1), 0), space.encode(&Action::Coup(3), 2));
+        assert_eq!(space.encode(&Action::Coup(0), 3), space.encode(&Action::Coup(1), 0));
+        let block = Action::Block(BlockAction::Assassinate(2), 1, Character::Contessa);
+        let shifted = Action::Block(BlockAction::Assassinate(0), 3, Character::Contessa);
+        assert_eq!(space.encode(&block, 2), space.encode(&shifted, 0));
+    }
+
+    #[test]
+    fn three_players_match_all_3players() {
+        let space = ActionSpace::new(3);
+        for player in 0..3 {
+            assert_eq!(space.actions(player), Action::all_3players(player, (player + 1) % 3, (player + 2) % 3));
+        }
+    }
+
+    #[test]
+    fn seats_outside_the_table_are_rejected() {
+        let space = ActionSpace::new(3);
+        assert_eq!(space.encode(&Action::Coup(3), 0), None);
+        assert_eq!(space.encode(&Action::Income, 3), None);
+        assert_eq!(space.decode(0, 3), None);
+        assert_eq!(space.decode(space.len(), 0), None);
+        assert_eq!(space.encode(&Action::Steal(3, 2), 0), None);
+        assert_eq!(space.encode(&Action::Block(BlockAction::Stealing(0, 2), 4, Character::Captain), 0), None);
+    }
+
+    #[test]
+    fn steal_amounts_follow_the_rules() {
+        assert_eq!(ActionSpace::new(3).encode(&Action::Steal(1, 3), 0), None);
+        let config = GameConfig { steal: 3, ..GameConfig::default() };
+        let space = ActionSpace::with_expansions(3, config.steal, false, false, false, false);
+        assert!(space.encode(&Action::Steal(1, 3), 0).is_some());
+        assert!(space.encode(&Action::Challenge(ChallengeAction::BlockStealing(2, 3), 2), 0).is_some());
+        assert_eq!(space.encode(&Action::Steal(1, 4), 0), None);
+
+        for seed in 0..10 {
+            let players: Vec<Box<dyn Player>> = (0..3).map(|i| Box::new(RandomPlayer::new(&format!("p{}", i), seed * 10 + i as u64)) as Box<dyn Player>).collect();
+            let mut game = GameState::new(players);
+            game.verbose = false;
+            game.rng = StdRng::seed_from_u64(seed);
+            game.decisions = Some(Vec::new());
+            game.set_rules(Ruleset::classic().with_config(config.clone()));
+            game::deal(&mut game);
+            assert_eq!(ActionSpace::for_game(&game).len(), space.len());
+            game.run();
+            for decision in game.decisions.take().unwrap() {
+                for option in decision.options.iter() {
+                    assert!(space.encode(option, decision.player).is_some(), "{} has no index", option);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_option_offered_in_play_is_encoded() {
+        for n in 2..=6 {
+            let space = ActionSpace::new(n);
+            for seed in 0..30 {
+                let players: Vec<Box<dyn Player>> = (0..n).map(|i| Box::new(RandomPlayer::new(&format!("p{}", i), seed * 10 + i as u64)) as Box<dyn Player>).collect();
+                let mut game = GameState::new(players);
+                game.verbose = false;
+                game.rng = StdRng::seed_from_u64(seed);
+                game.decisions = Some(Vec::new());
+                game::deal(&mut game);
+                game.run();
+
+                for decision in game.decisions.take().unwrap() {
+                    let legal = space.mask(&decision.options, decision.player);
+                    assert_eq!(legal.iter().filter(|&&l| l).count(), decision.options.len());
+                    for option in decision.options.iter() {
+                        let index = space.encode(option, decision.player).unwrap_or_else(|| panic!("{} players: {} has no index", n, option));
+                        assert_eq!(space.decode(index, decision.player).as_ref(), Some(option));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn expansion_options_are_encoded_after_the_base_space() {
+        let combos = [(true, false, false, false), (false, true, false, false), (true, true, false, false), (false, false, true, false), (false, false, false, true)];
+        for (reformation, inquisitor, giving, anarchy) in combos {
+            for n in 2..=6 {
+                let base = ActionSpace::new(n);
+                let space = ActionSpace::with_expansions(n, 2, reformation, inquisitor, giving, anarchy);
+                assert_eq!(space.actions(0)[..base.len()], base.actions(0)[..]);
+                for seed in 0..10 {
+                    let players: Vec<Box<dyn Player>> = (0..n).map(|i| Box::new(RandomPlayer::new(&format!("p{}", i), seed * 10 + i as u64)) as Box<dyn Player>).collect();
+                    let mut game = GameState::new(players);
+                    if giving {
+                        game = game.with_teams(Teams::alternating(n, 2).with_giving());
+                    }
+                    game.verbose = false;
+                    game.rng = StdRng::seed_from_u64(seed);
+                    game.decisions = Some(Vec::new());
+                    if inquisitor {
+                        game.enable_inquisitor();
+                    }
+                    if anarchy {
+                        let roles = if seed % 2 == 0 { [Character::Socialist, Character::Reporter] } else { [Character::Speculator, Character::Reporter] };
+                        game.set_rules(Ruleset::classic().with_roles(&roles).unwrap());
+                    }
+                    game::deal(&mut game);
+                    if reformation {
+                        game.enable_reformation();
+                    }
+                    assert_eq!(ActionSpace::for_game(&game).len(), space.len());
+                    game.run();
+
+                    for decision in game.decisions.take().unwrap() {
+                        for option in decision.options.iter() {
+                            let index = space.encode(option, decision.player).unwrap_or_else(|| panic!("{} players: {} has no index", n, option));
+                            assert_eq!(space.decode(index, decision.player).as_ref(), Some(option));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
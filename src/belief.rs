@@ -0,0 +1,453 @@
+use rand::Rng;
+
+use crate::action::Action;
+use crate::character::Character;
+use crate::event::Event;
+use crate::phase::Phase;
+use crate::ruleset::{ActionKind, Ruleset};
+
+/// Copies of each character, by `Character::slot`.
+type Hand = Vec<usize>;
+
+/// How a player is assumed to behave when their hand is unknown. Every rate is a
+/// probability per opportunity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BluffModel {
+    /// Claiming a character (by acting or blocking) without holding it.
+    pub bluff_rate: f64,
+    /// Challenging a claim, scaled up by one for every copy of the claimed character
+    /// the challenger holds.
+    pub challenge_rate: f64,
+    /// Letting an action pass although a held character could block it.
+    pub pass_with_blocker: f64,
+}
+
+impl BluffModel {
+    pub fn honest() -> BluffModel {
+        BluffModel { bluff_rate: 0.1, challenge_rate: 0.1, pass_with_blocker: 0.05 }
+    }
+
+    pub fn frequent_bluffer() -> BluffModel {
+        BluffModel { bluff_rate: 0.5, challenge_rate: 0.2, pass_with_blocker: 0.2 }
+    }
+
+    fn claim(&self, holds: bool) -> f64 {
+        if holds { 1.0 } else { self.bluff_rate }
+    }
+
+    fn challenge(&self, copies: usize) -> f64 {
+        (self.challenge_rate * (copies + 1) as f64).clamp(0.01, 0.99)
+    }
+}
+
+impl Default for BluffModel {
+    fn default() -> Self {
+        BluffModel::honest()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SeatBelief {
+    size: usize,
+    /// Likelihood of everything seen so far, per possible hand of `size` cards.
+    evidence: Vec<(Hand, f64)>,
+}
+
+impl SeatBelief {
+    fn new(size: usize) -> SeatBelief {
+        SeatBelief { size, evidence: hands_of_size(size).into_iter().map(|h| (h, 1.0)).collect() }
+    }
+
+    fn update<F: Fn(&Hand) -> f64>(&mut self, likelihood: F) {
+        for (hand, weight) in self.evidence.iter_mut() {
+            *weight *= likelihood(hand);
+        }
+        // Evidence that contradicts every hand means the model was wrong; start over.
+        if self.evidence.iter().all(|(_, w)| *w <= 0.0) {
+            *self = SeatBelief::new(self.size);
+        }
+    }
+}
+
+/// Probability distribution over every player's hidden hand, built from public events.
+///
+/// Each seat's hand is tracked separately: its posterior is the chance of drawing the
+/// hand from the cards this observer cannot see, times the likelihood of the seat's
+/// claims, blocks, challenges and passes under its [`BluffModel`]. Correlations between
+/// opponents (two players cannot both hold the last Duke) are only enforced when
+/// sampling with [`BeliefState::sample_hands`].
+#[derive(Debug, Clone)]
+pub struct BeliefState {
+    models: Vec<BluffModel>,
+    seats: Vec<SeatBelief>,
+    /// Copies of each character in the full deck.
+    deck: Hand,
+    /// The role in each slot, e.g. the Inquisitor in place of the Ambassador.
+    characters: Vec<Character>,
+    /// What claiming each character allows and blocks.
+    rules: Ruleset,
+    revealed: Hand,
+    /// The observer's own seat and current hand, if any.
+    known: Option<(usize, Hand)>,
+    /// The turn's action and the claim currently being answered, with who made them.
+    action: Option<(usize, Action)>,
+    claim: Option<(usize, Action)>,
+    consumed: usize,
+}
+
+impl BeliefState {
+    /// Belief of a spectator who has seen no cards, for the standard deck.
+    pub fn new(num_players: usize) -> BeliefState {
+        BeliefState::with_deck(num_players, &Ruleset::classic().deck())
+    }
+
+    /// Belief of a spectator for a game played by `rules`.
+    pub fn for_rules(num_players: usize, rules: &Ruleset) -> BeliefState {
+        let mut belief = BeliefState::with_deck(num_players, &rules.deck());
+        belief.seats = vec![SeatBelief::new(rules.config.influence); num_players];
+        belief.rules = rules.clone();
+        belief
+    }
+
+    /// Belief of a spectator for `deck`, reading claims by the base game's powers.
+    pub fn with_deck(num_players: usize, deck: &[Character]) -> BeliefState {
+        BeliefState {
+            models: vec![BluffModel::default(); num_players],
+            seats: vec![SeatBelief::new(2); num_players],
+            deck: to_hand(deck),
+            characters: Character::roles(deck),
+            rules: Ruleset::classic(),
+            revealed: vec![0; CHARACTERS],
+            known: None,
+            action: None,
+            claim: None,
+            consumed: 0,
+        }
+    }
+
+    pub fn set_model(&mut self, player: usize, model: BluffModel) {
+        self.models[player] = model;
+    }
+
+    pub fn model(&self, player: usize) -> BluffModel {
+        self.models[player]
+    }
+
+    /// Tells the belief which seat is observing and what it currently holds. Call again
+    /// whenever the hand changes, e.g. after an exchange.
+    pub fn observe_hand(&mut self, player: usize, hand: &[Character]) {
+        self.known = Some((player, to_hand(hand)));
+    }
+
+    /// Feeds the events not seen yet from a log that only ever grows, such as
+    /// `GameStateAI::events`.
+    pub fn sync(&mut self, events: &[Event]) {
+        for event in events.iter().skip(self.consumed) {
+            self.observe(event);
+        }
+        self.consumed = self.consumed.max(events.len());
+    }
+
+    pub fn observe(&mut self, event: &Event) {
+        match event {
+            Event::Played { player, action } => {
+                if action.is_action() {
+                    self.action = Some((*player, action.clone()));
+                }
+                let claimed = indices(&self.rules.claimed(action));
+                if !claimed.is_empty() {
+                    let model = self.models[*player];
+                    self.update(*player, |hand| model.claim(holds_any(hand, &claimed)));
+                    self.claim = Some((*player, action.clone()));
+                }
+                if *action == Action::Embezzle {
+                    let taxers = indices(&self.rules.claimants(ActionKind::Tax));
+                    let model = self.models[*player];
+                    self.update(*player, |hand| model.claim(!holds_any(hand, &taxers)));
+                }
+                if let Action::Challenge(challenge_action, _) = action {
+                    let claimed = indices(&self.rules.challenged(challenge_action));
+                    let model = self.models[*player];
+                    self.update(*player, |hand| model.challenge(copies(hand, &claimed)));
+                }
+            },
+            Event::Passed { player, phase: Phase::Challenge } => {
+                if let Some((_, claim)) = &self.claim {
+                    let claimed = indices(&self.rules.claimed(claim));
+                    let model = self.models[*player];
+                    self.update(*player, |hand| 1.0 - model.challenge(copies(hand, &claimed)));
+                }
+            },
+            Event::Passed { player, phase: Phase::Block } => {
+                let blockers = match &self.action {
+                    Some((_, Action::Assassinate(target))) if target == player => self.rules.blockers(ActionKind::Assassinate),
+                    Some((_, Action::Steal(target, _))) if target == player => self.rules.blockers(ActionKind::Steal),
+                    Some((_, Action::ForeignAid)) => self.rules.blockers(ActionKind::ForeignAid),
+                    _ => Vec::new(),
+                };
+                if !blockers.is_empty() {
+                    let blockers = indices(&blockers);
+                    let model = self.models[*player];
+                    self.update(*player, |hand| if holds_any(hand, &blockers) { model.pass_with_blocker } else { 1.0 - model.bluff_rate });
+                }
+            },
+            Event::Passed { .. } => {},
+            Event::ChallengeResolved { claimant, claim, proven, .. } => {
+                let claimed = indices(&self.rules.claimed(claim));
+                let proven = *proven;
+                // Embezzling claims not to hold any of the characters that collect Tax.
+                let embezzle = *claim == Action::Embezzle;
+                let taxers = indices(&self.rules.claimants(ActionKind::Tax));
+                self.update(*claimant, |hand| {
+                    let truthful = if embezzle { !holds_any(hand, &taxers) } else { holds_any(hand, &claimed) };
+                    if truthful == proven { 1.0 } else { 0.0 }
+                });
+            },
+            Event::Revealed { player, card } => {
+                let c = index(*card);
+                self.revealed[c] += 1;
+                if self.is_known(*player) {
+                    return;
+                }
+                // P(rest | revealed c) is proportional to P(rest + c); the 1/(copies+1)
+                // turns that back into evidence relative to drawing the smaller hand.
+                let seat = &mut self.seats[*player];
+                let mut shrunk = SeatBelief::new(seat.size.saturating_sub(1));
+                for (rest, weight) in shrunk.evidence.iter_mut() {
+                    let mut hand = rest.clone();
+                    hand[c] += 1;
+                    *weight = seat.evidence.iter().find(|(h, _)| *h == hand).map_or(0.0, |(_, w)| *w) / hand[c] as f64;
+                }
+                *seat = shrunk;
+                if seat.evidence.iter().all(|(_, w)| *w <= 0.0) {
+                    *seat = SeatBelief::new(seat.size);
+                }
+            },
+            Event::Exchanged { player } | Event::Drafted { player } => {
+                self.seats[*player] = SeatBelief::new(self.seats[*player].size);
+            },
+            Event::Seated { .. } | Event::FirstPlayer { .. } | Event::Dealt { .. } => {},
+        }
+    }
+
+    fn is_known(&self, player: usize) -> bool {
+        matches!(&self.known, Some((seat, _)) if *seat == player)
+    }
+
+    fn update<F: Fn(&Hand) -> f64>(&mut self, player: usize, likelihood: F) {
+        if !self.is_known(player) {
+            self.seats[player].update(likelihood);
+        }
+    }
+
+    /// Copies of each character this observer cannot see.
+    fn unseen(&self) -> Hand {
+        let mut unseen: Hand = self.deck.iter().zip(self.revealed.iter()).map(|(d, r)| d.saturating_sub(*r)).collect();
+        if let Some((_, hand)) = &self.known {
+            for (u, h) in unseen.iter_mut().zip(hand.iter()) {
+                *u = u.saturating_sub(*h);
+            }
+        }
+        unseen
+    }
+
+    fn posterior(&self, player: usize, unseen: &Hand) -> Vec<(Hand, f64)> {
+        if let Some((seat, hand)) = &self.known {
+            if *seat == player {
+                return vec![(hand.clone(), 1.0)];
+            }
+        }
+        let evidence = &self.seats[player].evidence;
+        let mut weights: Vec<f64> = evidence.iter().map(|(h, w)| w * draw_weight(unseen, h)).collect();
+        if weights.iter().sum::<f64>() <= 0.0 {
+            weights = evidence.iter().map(|(h, _)| draw_weight(unseen, h)).collect();
+        }
+        let total: f64 = weights.iter().sum();
+        evidence.iter().zip(weights).map(|((h, _), w)| (h.clone(), if total > 0.0 { w / total } else { 0.0 })).collect()
+    }
+
+    /// Every hand `player` might hold with its probability, most likely first.
+    pub fn hand_probabilities(&self, player: usize) -> Vec<(Vec<Character>, f64)> {
+        let mut hands: Vec<(Vec<Character>, f64)> = self.posterior(player, &self.unseen()).into_iter()
+            .filter(|(_, p)| *p > 0.0)
+            .map(|(h, p)| (to_cards(&self.characters, &h), p))
+            .collect();
+        hands.sort_by(|a, b| b.1.total_cmp(&a.1));
+        hands
+    }
+
+    /// P(`player` holds at least one `character`).
+    pub fn probability(&self, player: usize, character: Character) -> f64 {
+        let c = index(character);
+        self.posterior(player, &self.unseen()).iter().filter(|(h, _)| h[c] > 0).map(|(_, p)| p).sum()
+    }
+
+    pub fn expected_copies(&self, player: usize, character: Character) -> f64 {
+        let c = index(character);
+        self.posterior(player, &self.unseen()).iter().map(|(h, p)| h[c] as f64 * p).sum()
+    }
+
+    /// Draws one hand per seat that fits together with the unseen cards, visiting seats
+    /// in random order and weighting each by its evidence. The observer's seat gets its
+    /// own hand.
+    pub fn sample_hands<R: Rng>(&self, rng: &mut R) -> Vec<Vec<Character>> {
+        let mut remaining = self.unseen();
+        let mut hands = vec![Vec::new(); self.seats.len()];
+        let mut order: Vec<usize> = (0..self.seats.len()).collect();
+        rand::seq::SliceRandom::shuffle(order.as_mut_slice(), rng);
+        for player in order {
+            let posterior = self.posterior(player, &remaining);
+            if self.is_known(player) {
+                hands[player] = to_cards(&self.characters, &posterior[0].0);
+                continue;
+            }
+            let weights: Vec<f64> = posterior.iter().map(|(h, p)| if fits(&remaining, h) { *p } else { 0.0 }).collect();
+            let total: f64 = weights.iter().sum();
+            let chosen = if total > 0.0 {
+                let mut x = rng.gen::<f64>() * total;
+                weights.iter().position(|w| { x -= w; x < 0.0 }).unwrap_or(weights.len() - 1)
+            } else {
+                // Fall back to any hand that fits, ignoring the evidence.
+                match posterior.iter().position(|(h, _)| fits(&remaining, h)) {
+                    Some(i) => i,
+                    None => continue,
+                }
+            };
+            let hand = &posterior[chosen].0;
+            for (r, h) in remaining.iter_mut().zip(hand.iter()) {
+                *r -= h;
+            }
+            hands[player] = to_cards(&self.characters, hand);
+        }
+        hands
+    }
+}
+
+const CHARACTERS: usize = 5;
+
+fn index(character: Character) -> usize {
+    character.slot()
+}
+
+/// Slots of `characters`, each once: a claim naming both the Ambassador and the
+/// Inquisitor is about a single slot.
+fn indices(characters: &[Character]) -> Vec<usize> {
+    let mut slots: Vec<usize> = characters.iter().map(|&c| index(c)).collect();
+    slots.sort_unstable();
+    slots.dedup();
+    slots
+}
+
+fn to_hand(cards: &[Character]) -> Hand {
+    let mut hand = vec![0; CHARACTERS];
+    for &card in cards {
+        hand[index(card)] += 1;
+    }
+    hand
+}
+
+fn to_cards(characters: &[Character], hand: &Hand) -> Vec<Character> {
+    characters.iter().copied().zip(hand.iter()).flat_map(|(c, &n)| std::iter::repeat_n(c, n)).collect()
+}
+
+fn holds_any(hand: &Hand, characters: &[usize]) -> bool {
+    characters.iter().any(|&c| hand[c] > 0)
+}
+
+fn copies(hand: &Hand, characters: &[usize]) -> usize {
+    characters.iter().map(|&c| hand[c]).sum()
+}
+
+fn fits(unseen: &Hand, hand: &Hand) -> bool {
+    hand.iter().zip(unseen.iter()).all(|(h, u)| h <= u)
+}
+
+/// Relative chance of drawing `hand` from `unseen`: the product of C(unseen, copies).
+fn draw_weight(unseen: &Hand, hand: &Hand) -> f64 {
+    hand.iter().zip(unseen.iter()).map(|(&h, &u)| binomial(u, h)).product()
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+/// Every multiset of `size` characters.
+fn hands_of_size(size: usize) -> Vec<Hand> {
+    fn extend(from: usize, left: usize, hand: &mut Hand, out: &mut Vec<Hand>) {
+        if left == 0 {
+            out.push(hand.clone());
+            return;
+        }
+        for c in from..CHARACTERS {
+            hand[c] += 1;
+            extend(c, left - 1, hand, out);
+            hand[c] -= 1;
+        }
+    }
+    let mut out = Vec::new();
+    extend(0, size, &mut vec![0; CHARACTERS], &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::BeliefState;
+    use crate::action::{Action, ChallengeAction};
+    use crate::character::Character;
+    use crate::event::Event;
+    use crate::ruleset::Ruleset;
+
+    #[test]
+    fn a_revealed_card_leaves_every_hand() {
+        let mut belief = BeliefState::new(3);
+        belief.observe_hand(0, &[Character::Duke, Character::Duke]);
+        belief.observe(&Event::Revealed { player: 1, card: Character::Duke });
+        //all three Dukes are accounted for
+        for player in 1..3 {
+            assert_eq!(belief.probability(player, Character::Duke), 0.0);
+            assert!(belief.hand_probabilities(player).iter().all(|(hand, _)| !hand.contains(&Character::Duke)));
+        }
+    }
+
+    #[test]
+    fn a_settled_challenge_pins_the_claim() {
+        let challenged = |proven: bool| {
+            let mut belief = BeliefState::new(3);
+            belief.sync(&[
+                Event::Played { player: 1, action: Action::Tax },
+                Event::Played { player: 2, action: Action::Challenge(ChallengeAction::Tax, 1) },
+                Event::ChallengeResolved { claimant: 1, challenger: 2, claim: Action::Tax, proven },
+            ]);
+            belief.probability(1, Character::Duke)
+        };
+        assert!((challenged(true) - 1.0).abs() < 1e-9);
+        assert_eq!(challenged(false), 0.0);
+    }
+
+    #[test]
+    fn sampled_hands_never_overdraw_the_deck() {
+        let deck = Ruleset::classic().deck();
+        let mut belief = BeliefState::new(6);
+        belief.observe_hand(0, &[Character::Duke, Character::Captain]);
+        //everyone claims Duke, so the evidence alone would give out more than exist
+        for player in 1..6 {
+            belief.observe(&Event::Played { player, action: Action::Tax });
+        }
+        belief.observe(&Event::Revealed { player: 5, card: Character::Duke });
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let hands = belief.sample_hands(&mut rng);
+            for character in Character::all() {
+                let dealt = hands.iter().flatten().filter(|&&c| c == character).count();
+                let revealed = usize::from(character == Character::Duke);
+                assert!(dealt + revealed <= deck.iter().filter(|&&c| c == character).count());
+            }
+        }
+    }
+}
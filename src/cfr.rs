@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::action::Action;
+use crate::character::Character;
+use crate::config::GameConfig;
+use crate::game::{self, GameState};
+use crate::setup::{SeatOrder, Setup};
+use crate::gameai::GameStateAI;
+use crate::player::Player;
+use crate::player::cfr::CfrPlayer;
+use crate::player::heuristic::{HeuristicPlayer, Personality};
+use crate::probe::{advance, new_game, Advance};
+
+/// Settings shared by training and evaluation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CfrConfig {
+    pub players: usize,
+    /// Games are cut off after this many turns and scored by remaining influence.
+    pub max_turns: usize,
+    pub seed: u64,
+}
+
+impl Default for CfrConfig {
+    fn default() -> Self {
+        CfrConfig {
+            players: 2,
+            max_turns: 4,
+            seed: 0,
+        }
+    }
+}
+
+/// Coins are abstracted into the ranges that change what a player can do, with the
+/// official costs 0-1, 2, 3-4 (assassinate), 5-6, 7-9 (coup) and 10+ (forced coup).
+fn coin_bucket(coins: usize, config: &GameConfig) -> usize {
+    let thresholds = [2, config.assassinate_cost, config.assassinate_cost + 2, config.coup_cost, config.forced_coup];
+    thresholds.iter().filter(|&&t| coins >= t).count()
+}
+
+/// Abstract information set of the player deciding in `game_state`: phase, coin bucket
+/// and influence per relative seat, own hand, revealed cards and the claim being
+/// answered. Seats are relative so that one entry covers every seat. A block enters
+/// the key as announced, naming the character claimed, so whether the blocker holds
+/// it stays private.
+pub fn infoset_key(game_state: &GameStateAI) -> String {
+    let n = game_state.players_lives.len();
+    let seats: Vec<String> = (0..n).map(|offset| {
+        let seat = (game_state.player_index + offset) % n;
+        format!("{}:{}", coin_bucket(game_state.players_coins[seat], &game_state.rules.config), game_state.players_lives[seat])
+    }).collect();
+    let mut hand: Vec<String> = game_state.cards_in_hand.iter().map(|c| c.to_string()).collect();
+    hand.sort();
+    let revealed: Vec<String> = Character::all().into_iter()
+        .map(|character| game_state.revealed_characters.iter().filter(|&&c| c == Some(character)).count().to_string())
+        .collect();
+    let claim = match &game_state.pending_claim {
+        Some((action, player)) => format!("{}@{}", action_key(game_state, action), game_state.relative_seat(*player)),
+        None => "-".to_string(),
+    };
+    format!("{:?}|{}|{}|{}|{}", game_state.phase, seats.join(","), hand.join("+"), revealed.join(""), claim)
+}
+
+/// `action` as seen from the deciding player, with seats relative to them.
+pub fn action_key(game_state: &GameStateAI, action: &Action) -> String {
+    format!("{:?}", action.map_seats(|seat| game_state.relative_seat(seat)))
+}
+
+/// Action probabilities per information set, as played by [`CfrPlayer`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Strategy {
+    pub table: HashMap<String, Vec<(String, f64)>>,
+}
+
+impl Strategy {
+    pub fn new() -> Strategy {
+        Strategy { table: HashMap::new() }
+    }
+
+    pub fn probabilities(&self, infoset: &str) -> Option<&Vec<(String, f64)>> {
+        self.table.get(infoset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Writes one information set per line as `key<TAB>action=p|action=p|...`, sorted by key.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+        let mut keys: Vec<&String> = self.table.keys().collect();
+        keys.sort();
+        for key in keys {
+            let actions: Vec<String> = self.table[key].iter().map(|(a, p)| format!("{}={}", a, p)).collect();
+            writeln!(out, "{}\t{}", key, actions.join("|")).map_err(|e| e.to_string())?;
+        }
+        out.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Strategy, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut strategy = Strategy::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |e: &str| format!("line {}: {}", i + 1, e);
+            let (key, actions) = line.split_once('\t').ok_or_else(|| error("missing tab"))?;
+            let mut probabilities = Vec::new();
+            for entry in actions.split('|') {
+                let (action, p) = entry.rsplit_once('=').ok_or_else(|| error("expected action=probability"))?;
+                probabilities.push((action.to_string(), p.parse::<f64>().map_err(|e| error(&e.to_string()))?));
+            }
+            strategy.table.insert(key.to_string(), probabilities);
+        }
+        Ok(strategy)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Entry {
+    regret: f64,
+    strategy_sum: f64,
+}
+
+/// External-sampling Monte Carlo CFR over the engine's game tree.
+///
+/// Each iteration deals a new game (the chance sample) and, for every seat in turn,
+/// walks all of that seat's actions while sampling the other seats from their current
+/// strategies. Nodes are reached by copying the game before the step in which the
+/// decision happens and replaying the decisions already taken during that step.
+pub struct CfrSolver {
+    pub config: CfrConfig,
+    pub iterations: usize,
+    infosets: HashMap<String, Vec<(String, Entry)>>,
+    rng: StdRng,
+}
+
+impl CfrSolver {
+    pub fn new(config: CfrConfig) -> CfrSolver {
+        CfrSolver {
+            config,
+            iterations: 0,
+            infosets: HashMap::new(),
+            rng: StdRng::seed_from_u64(config.seed),
+        }
+    }
+
+    pub fn num_infosets(&self) -> usize {
+        self.infosets.len()
+    }
+
+    pub fn run(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            let root = new_game(self.config.players, self.rng.gen());
+            for traverser in 0..self.config.players {
+                self.traverse(&root, &[], traverser);
+            }
+            self.iterations += 1;
+        }
+    }
+
+    /// The average strategy, which is what converges towards an equilibrium.
+    pub fn average_strategy(&self) -> Strategy {
+        let mut strategy = Strategy::new();
+        for (key, entries) in self.infosets.iter() {
+            let total: f64 = entries.iter().map(|(_, e)| e.strategy_sum).sum();
+            let probabilities = entries.iter()
+                .map(|(a, e)| (a.clone(), if total > 0.0 { e.strategy_sum / total } else { 1.0 / entries.len() as f64 }))
+                .collect();
+            strategy.table.insert(key.clone(), probabilities);
+        }
+        strategy
+    }
+
+    /// Regret matching over the options legal at this node.
+    fn current_strategy(&mut self, key: &str, actions: &[String]) -> Vec<f64> {
+        let entries = self.infosets.entry(key.to_string()).or_default();
+        for action in actions {
+            if !entries.iter().any(|(a, _)| a == action) {
+                entries.push((action.clone(), Entry::default()));
+            }
+        }
+        let positive: Vec<f64> = actions.iter()
+            .map(|action| entries.iter().find(|(a, _)| a == action).map_or(0.0, |(_, e)| e.regret.max(0.0)))
+            .collect();
+        let total: f64 = positive.iter().sum();
+        if total > 0.0 {
+            positive.iter().map(|r| r / total).collect()
+        } else {
+            vec![1.0 / actions.len() as f64; actions.len()]
+        }
+    }
+
+    fn entry(&mut self, key: &str, action: &str) -> &mut Entry {
+        let entries = self.infosets.get_mut(key).expect("unknown infoset");
+        let index = entries.iter().position(|(a, _)| a == action).expect("unknown action");
+        &mut entries[index].1
+    }
+
+    fn traverse(&mut self, game: &GameState, script: &[Action], traverser: usize) -> f64 {
+        if let Some(utilities) = leaf_utilities(game, self.config.max_turns) {
+            return utilities[traverser];
+        }
+        let pending = match advance(game, script) {
+            Advance::Stepped(next) => return self.traverse(&next, &[], traverser),
+            Advance::Decision(pending) => pending,
+        };
+
+        let key = infoset_key(&pending.observation);
+        let actions: Vec<String> = pending.options.iter().map(|a| action_key(&pending.observation, a)).collect();
+        let strategy = self.current_strategy(&key, &actions);
+        let mut next_script = script.to_vec();
+
+        if pending.player == traverser {
+            let mut values = Vec::with_capacity(actions.len());
+            for option in pending.options.iter() {
+                next_script.push(option.clone());
+                values.push(self.traverse(game, &next_script, traverser));
+                next_script.pop();
+            }
+            let value: f64 = values.iter().zip(strategy.iter()).map(|(v, p)| v * p).sum();
+            for (action, v) in actions.iter().zip(values.iter()) {
+                self.entry(&key, action).regret += v - value;
+            }
+            value
+        } else {
+            for (action, p) in actions.iter().zip(strategy.iter()) {
+                self.entry(&key, action).strategy_sum += p;
+            }
+            let index = sample(&strategy, &mut self.rng);
+            next_script.push(pending.options[index].clone());
+            self.traverse(game, &next_script, traverser)
+        }
+    }
+}
+
+/// Estimates how much a best-responding opponent gains against `strategy` in the
+/// 2-player game: a best response is approximated from `samples` sampled traversals
+/// and then played against `strategy` for `samples` games. The game is symmetric, so
+/// an unexploitable strategy scores 0; the estimate is a lower bound on the true value.
+pub fn exploitability(strategy: &Strategy, config: &CfrConfig, samples: usize) -> Result<f64, String> {
+    if config.players != 2 {
+        return Err("Exploitability is only estimated for 2 players".to_string());
+    }
+    let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(1));
+
+    let fallback = HeuristicPlayer::new("fallback", Personality::honest(), rng.gen());
+    let opponent = |observation: &GameStateAI, options: &[Action]| policy(strategy, &fallback, observation, options);
+    let mut q: HashMap<String, Vec<(String, f64, f64)>> = HashMap::new();
+    for i in 0..samples {
+        let root = new_game(2, rng.gen());
+        best_response_traverse(&mut q, &opponent, &root, &[], i % 2, config.max_turns, &mut rng);
+    }
+    let mut best_response = Strategy::new();
+    for (key, values) in q.iter() {
+        if let Some(best) = best_action(values) {
+            best_response.table.insert(key.clone(), vec![(best, 1.0)]);
+        }
+    }
+
+    let strategy = Rc::new(strategy.clone());
+    let best_response = Rc::new(best_response);
+    let mut total = 0.0;
+    for i in 0..samples {
+        //the best response alternates between the two seats
+        let seat = i % 2;
+        let mut players: Vec<Box<dyn Player>> = vec![
+            Box::new(CfrPlayer::new("strategy", Rc::clone(&strategy), rng.gen())),
+            Box::new(CfrPlayer::new("best response", Rc::clone(&best_response), rng.gen())),
+        ];
+        if seat == 0 {
+            players.reverse();
+        }
+        let mut game = GameState::new(players);
+        game.verbose = false;
+        game.rng = StdRng::seed_from_u64(rng.gen());
+        game::start_game_with(&mut game, &Setup::default().with_seating(SeatOrder::Fixed))?;
+        let utilities = loop {
+            if let Some(utilities) = leaf_utilities(&game, config.max_turns) {
+                break utilities;
+            }
+            game.step();
+        };
+        total += utilities[seat];
+    }
+    Ok(total / samples.max(1) as f64)
+}
+
+/// Mean sampled value of an action, skipping actions never tried.
+fn best_action(values: &[(String, f64, f64)]) -> Option<String> {
+    values.iter()
+        .filter(|(_, _, count)| *count > 0.0)
+        .max_by(|a, b| (a.1 / a.2).total_cmp(&(b.1 / b.2)))
+        .map(|(a, _, _)| a.clone())
+}
+
+/// Walks every action of `responder` and samples the other seat from `opponent`,
+/// accumulating sampled action values. Below the current node `responder` plays the
+/// best action found so far, so values are not taken from hands it could not see.
+fn best_response_traverse(q: &mut HashMap<String, Vec<(String, f64, f64)>>, opponent: &dyn Fn(&GameStateAI, &[Action]) -> Vec<f64>, game: &GameState, script: &[Action], responder: usize, max_turns: usize, rng: &mut StdRng) -> f64 {
+    if let Some(utilities) = leaf_utilities(game, max_turns) {
+        return utilities[responder];
+    }
+    let pending = match advance(game, script) {
+        Advance::Stepped(next) => return best_response_traverse(q, opponent, &next, &[], responder, max_turns, rng),
+        Advance::Decision(pending) => pending,
+    };
+
+    let key = infoset_key(&pending.observation);
+    let actions: Vec<String> = pending.options.iter().map(|a| action_key(&pending.observation, a)).collect();
+    let mut next_script = script.to_vec();
+
+    if pending.player == responder {
+        let mut values = Vec::with_capacity(actions.len());
+        for option in pending.options.iter() {
+            next_script.push(option.clone());
+            values.push(best_response_traverse(q, opponent, game, &next_script, responder, max_turns, rng));
+            next_script.pop();
+        }
+        let entries = q.entry(key).or_default();
+        for (action, v) in actions.iter().zip(values.iter()) {
+            match entries.iter_mut().find(|(a, _, _)| a == action) {
+                Some(entry) => {
+                    entry.1 += v;
+                    entry.2 += 1.0;
+                },
+                None => entries.push((action.clone(), *v, 1.0)),
+            }
+        }
+        let legal: Vec<(String, f64, f64)> = entries.iter().filter(|(a, _, _)| actions.contains(a)).cloned().collect();
+        let best = best_action(&legal).and_then(|best| actions.iter().position(|a| *a == best)).unwrap_or(0);
+        values[best]
+    } else {
+        let probabilities = opponent(&pending.observation, &pending.options);
+        next_script.push(pending.options[sample(&probabilities, rng)].clone());
+        best_response_traverse(q, opponent, game, &next_script, responder, max_turns, rng)
+    }
+}
+
+/// How a `CfrPlayer` plays `options`: the probabilities `strategy` assigns to them, or
+/// whatever `fallback` picks when the strategy does not cover the information set.
+pub fn policy(strategy: &Strategy, fallback: &HeuristicPlayer, game_state: &GameStateAI, options: &[Action]) -> Vec<f64> {
+    if strategy.probabilities(&infoset_key(game_state)).is_none() {
+        let choice = fallback.choose_action(options.to_vec(), game_state);
+        return options.iter().map(|option| if *option == choice { 1.0 } else { 0.0 }).collect();
+    }
+    strategy_for(strategy, game_state, options)
+}
+
+/// Probabilities `strategy` assigns to `options`, uniform if it gives none of them weight.
+fn strategy_for(strategy: &Strategy, game_state: &GameStateAI, options: &[Action]) -> Vec<f64> {
+    let probabilities = strategy.probabilities(&infoset_key(game_state));
+    let weights: Vec<f64> = options.iter().map(|option| {
+        let key = action_key(game_state, option);
+        probabilities.and_then(|ps| ps.iter().find(|(a, _)| *a == key)).map_or(0.0, |(_, p)| *p)
+    }).collect();
+    let total: f64 = weights.iter().sum();
+    if total > 0.0 {
+        weights.iter().map(|w| w / total).collect()
+    } else {
+        vec![1.0 / options.len() as f64; options.len()]
+    }
+}
+
+fn sample(probabilities: &[f64], rng: &mut StdRng) -> usize {
+    let mut x: f64 = rng.gen();
+    for (i, p) in probabilities.iter().enumerate() {
+        if x < *p {
+            return i;
+        }
+        x -= p;
+    }
+    probabilities.len() - 1
+}
+
+/// Zero-sum payoffs once the game is over or has reached `max_turns`: the winner gets
+/// n-1 and everyone else -1; at the cut-off, or when a stopped game has no single
+/// winner, influence is shared out proportionally.
+fn leaf_utilities(game: &GameState, max_turns: usize) -> Option<Vec<f64>> {
+    let n = game.players.len() as f64;
+    if game.game_over() {
+        if let Some(winner) = game.winning_player() {
+            return Some((0..game.players.len()).map(|i| if i == winner { n - 1.0 } else { -1.0 }).collect());
+        }
+    } else if game.turn.number < max_turns {
+        return None;
+    }
+    let lives: Vec<f64> = game.players.iter().map(|p| p.hand().len() as f64).collect();
+    let total: f64 = lives.iter().sum();
+    Some(lives.iter().map(|l| n * l / total - 1.0).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CfrConfig, CfrSolver, Entry};
+
+    #[test]
+    fn regret_matching_gives_a_distribution() {
+        let mut solver = CfrSolver::new(CfrConfig::default());
+        let actions: Vec<String> = ["a", "b", "c"].iter().map(|a| a.to_string()).collect();
+        let regrets = [2.0, -1.0, 1.0];
+        solver.infosets.insert("key".to_string(), actions.iter().cloned().zip(regrets.iter().map(|&regret| Entry { regret, strategy_sum: 0.0 })).collect());
+        assert_eq!(solver.current_strategy("key", &actions), vec![2.0 / 3.0, 0.0, 1.0 / 3.0]);
+
+        //no positive regret falls back to uniform, new actions included
+        let more: Vec<String> = ["b", "d"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(solver.current_strategy("key", &more), vec![0.5, 0.5]);
+
+        solver.run(10);
+        for probabilities in solver.average_strategy().table.values() {
+            assert!(probabilities.iter().all(|(_, p)| *p >= 0.0));
+            assert!((probabilities.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn seeded_runs_learn_the_same_strategy() {
+        let config = CfrConfig { seed: 5, ..CfrConfig::default() };
+        let mut first = CfrSolver::new(config);
+        let mut second = CfrSolver::new(config);
+        first.run(20);
+        second.run(20);
+        let (first, second) = (first.average_strategy(), second.average_strategy());
+        let mut keys: Vec<&String> = first.table.keys().collect();
+        keys.sort();
+        let key = keys[0];
+        assert_eq!(first.probabilities(key), second.probabilities(key));
+        assert_eq!(first, second);
+    }
+}
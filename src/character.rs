@@ -1,25 +1,57 @@
-#[derive(Debug, Copy, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Character {
     Duke,
     Assassin,
     Captain,
     Ambassador,
     Contessa,
+    /// Replaces the Ambassador in Inquisitor games.
+    Inquisitor,
+    /// Anarchy pack: replaces the Duke.
+    Socialist,
+    /// Anarchy pack: replaces the Duke.
+    Speculator,
+    /// Anarchy pack: replaces the Ambassador.
+    Reporter,
 }
 
 impl Character {
+    /// The five roles of the base game.
     pub fn all() -> Vec<Character> {
         vec![Character::Duke, Character::Assassin, Character::Captain, Character::Ambassador, Character::Contessa]
     }
 
-    pub fn create_deck() -> Vec<Character> {
+    /// The five roles of an Inquisitor game.
+    pub fn inquisition() -> Vec<Character> {
+        vec![Character::Duke, Character::Assassin, Character::Captain, Character::Inquisitor, Character::Contessa]
+    }
+
+    /// Position among the five roles of a game, an alternative role taking the slot of
+    /// the base role it replaces.
+    pub fn slot(&self) -> usize {
+        match self {
+            Character::Duke | Character::Socialist | Character::Speculator => 0,
+            Character::Assassin => 1,
+            Character::Captain => 2,
+            Character::Ambassador | Character::Inquisitor | Character::Reporter => 3,
+            Character::Contessa => 4,
+        }
+    }
+
+    /// The roles found in `deck`, one per slot, falling back to the base game's.
+    pub fn roles(deck: &[Character]) -> Vec<Character> {
+        Character::all().into_iter()
+            .map(|c| deck.iter().copied().find(|d| d.slot() == c.slot()).unwrap_or(c))
+            .collect()
+    }
+
+    /// Three copies of each of `characters`.
+    pub fn deck(characters: &[Character]) -> Vec<Character> {
         let mut deck = Vec::new();
         for _ in 0..3 {
-            deck.push(Character::Duke);
-            deck.push(Character::Assassin);
-            deck.push(Character::Captain);
-            deck.push(Character::Ambassador);
-            deck.push(Character::Contessa);
+            deck.extend_from_slice(characters);
         }
         deck
     }
@@ -33,6 +65,10 @@ impl std::fmt::Display for Character {
             Character::Captain => write!(f, "Captain"),
             Character::Ambassador => write!(f, "Ambassador"),
             Character::Contessa => write!(f, "Contessa"),
+            Character::Inquisitor => write!(f, "Inquisitor"),
+            Character::Socialist => write!(f, "Socialist"),
+            Character::Speculator => write!(f, "Speculator"),
+            Character::Reporter => write!(f, "Reporter"),
         }
     }
-}
\ No newline at end of file
+}
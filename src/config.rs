@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Stalemate window of the batch runners (simulations, self-play, tournaments and
+/// matches), so that players who never attack cannot keep them running forever.
+pub const STALEMATE_ROUNDS: usize = 8;
+
+/// House rules for coins and influence. The defaults are the official values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub starting_coins: usize,
+    /// Cards dealt to every player.
+    pub influence: usize,
+    pub income: usize,
+    pub foreign_aid: usize,
+    pub tax: usize,
+    /// Most coins a Steal takes.
+    pub steal: usize,
+    pub assassinate_cost: usize,
+    pub coup_cost: usize,
+    /// Coins at which a player must Coup.
+    pub forced_coup: usize,
+    /// Turns after which the game stops and goes to the tiebreak.
+    pub max_turns: Option<usize>,
+    /// Rounds without anyone losing influence after which the game stops and goes to the
+    /// tiebreak. A round is one turn for every player still in.
+    pub stalemate_rounds: Option<usize>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            starting_coins: 2,
+            influence: 2,
+            income: 1,
+            foreign_aid: 2,
+            tax: 3,
+            steal: 2,
+            assassinate_cost: 3,
+            coup_cost: 7,
+            forced_coup: 10,
+            max_turns: None,
+            stalemate_rounds: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Checks that every player can be dealt a hand and is able to Coup.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.influence == 0 {
+            return Err("Players need at least one influence".to_string());
+        }
+        if self.coup_cost > self.forced_coup {
+            return Err("The forced Coup threshold is below the Coup cost".to_string());
+        }
+        if self.max_turns == Some(0) || self.stalemate_rounds == Some(0) {
+            return Err("Turn and stalemate limits must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Magic bytes at the start of the binary dataset format.
+const BINARY_MAGIC: &[u8; 8] = b"COUPDS01";
+
+/// One recorded decision: the encoded observation, which labels were legal, the label
+/// of the chosen action and the eventual return of the player who chose it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub features: Vec<f64>,
+    pub legal: Vec<bool>,
+    pub label: usize,
+    pub outcome: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dataset {
+    pub samples: Vec<Sample>,
+}
+
+impl Dataset {
+    pub fn new() -> Dataset {
+        Dataset { samples: Vec::new() }
+    }
+
+    /// Adds a sample that only carries features and a label, as used for plain classification.
+    pub fn push(&mut self, features: Vec<f64>, label: usize) {
+        self.samples.push(Sample { features, legal: Vec::new(), label, outcome: 0.0 });
+    }
+
+    pub fn push_sample(&mut self, sample: Sample) {
+        self.samples.push(sample);
+    }
+
+    pub fn extend(&mut self, other: Dataset) {
+        self.samples.extend(other.samples);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Number of features per sample, or an error if the samples disagree.
+    pub fn feature_len(&self) -> Result<usize, String> {
+        let len = self.samples.first().map(|s| s.features.len()).unwrap_or(0);
+        if self.samples.iter().any(|s| s.features.len() != len) {
+            return Err("Samples have different feature lengths".to_string());
+        }
+        Ok(len)
+    }
+
+    /// Length of the legal-action masks, or an error if the samples disagree.
+    pub fn mask_len(&self) -> Result<usize, String> {
+        let len = self.samples.first().map(|s| s.legal.len()).unwrap_or(0);
+        if self.samples.iter().any(|s| s.legal.len() != len) {
+            return Err("Samples have different mask lengths".to_string());
+        }
+        Ok(len)
+    }
+
+    /// Writes one sample per line as `label,outcome,f0,f1,...,m0,m1,...` after a header row.
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+        let mut header = vec!["label".to_string(), "outcome".to_string()];
+        header.extend((0..self.feature_len()?).map(|i| format!("f{}", i)));
+        header.extend((0..self.mask_len()?).map(|i| format!("m{}", i)));
+        writeln!(out, "{}", header.join(",")).map_err(|e| e.to_string())?;
+        for sample in self.samples.iter() {
+            let mut fields = vec![sample.label.to_string(), sample.outcome.to_string()];
+            fields.extend(sample.features.iter().map(|f| f.to_string()));
+            fields.extend(sample.legal.iter().map(|&m| if m { "1" } else { "0" }.to_string()));
+            writeln!(out, "{}", fields.join(",")).map_err(|e| e.to_string())?;
+        }
+        out.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn load_csv<P: AsRef<Path>>(path: P) -> Result<Dataset, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(file).lines();
+        let header = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => return Ok(Dataset::new()),
+        };
+        let num_features = header.split(',').filter(|h| h.starts_with('f')).count();
+        let num_mask = header.split(',').filter(|h| h.starts_with('m')).count();
+
+        let mut dataset = Dataset::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |e: String| format!("line {}: {}", i + 2, e);
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != 2 + num_features + num_mask {
+                return Err(error(format!("expected {} fields, found {}", 2 + num_features + num_mask, fields.len())));
+            }
+            let label = fields[0].parse::<usize>().map_err(|e| error(e.to_string()))?;
+            let outcome = fields[1].parse::<f64>().map_err(|e| error(e.to_string()))?;
+            let features = fields[2..2 + num_features].iter().map(|f| f.parse::<f64>()).collect::<Result<Vec<f64>, _>>().map_err(|e| error(e.to_string()))?;
+            let legal = fields[2 + num_features..].iter().map(|&m| m == "1").collect();
+            dataset.push_sample(Sample { features, legal, label, outcome });
+        }
+        Ok(dataset)
+    }
+
+    /// Writes the compact little-endian format: magic, sample/feature/mask counts as u32,
+    /// then per sample the label as u32, the outcome and features as f32 and the mask
+    /// packed eight labels per byte.
+    pub fn save_binary<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+        let num_features = self.feature_len()?;
+        let num_mask = self.mask_len()?;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(BINARY_MAGIC);
+        for count in [self.samples.len(), num_features, num_mask] {
+            bytes.extend_from_slice(&(count as u32).to_le_bytes());
+        }
+        for sample in self.samples.iter() {
+            bytes.extend_from_slice(&(sample.label as u32).to_le_bytes());
+            bytes.extend_from_slice(&(sample.outcome as f32).to_le_bytes());
+            for feature in sample.features.iter() {
+                bytes.extend_from_slice(&(*feature as f32).to_le_bytes());
+            }
+            for chunk in sample.legal.chunks(8) {
+                let byte = chunk.iter().enumerate().fold(0u8, |byte, (bit, &legal)| if legal { byte | 1 << bit } else { byte });
+                bytes.push(byte);
+            }
+        }
+        out.write_all(&bytes).map_err(|e| e.to_string())?;
+        out.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn load_binary<P: AsRef<Path>>(path: P) -> Result<Dataset, String> {
+        let mut bytes = Vec::new();
+        File::open(path).map_err(|e| e.to_string())?.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        if bytes.len() < 20 || &bytes[..8] != BINARY_MAGIC {
+            return Err("Not a coup binary dataset".to_string());
+        }
+
+        let mut pos = 8;
+        let num_samples = read_u32(&bytes, &mut pos)? as usize;
+        let num_features = read_u32(&bytes, &mut pos)? as usize;
+        let num_mask = read_u32(&bytes, &mut pos)? as usize;
+        let mask_bytes = num_mask.div_ceil(8);
+
+        let mut dataset = Dataset::new();
+        for _ in 0..num_samples {
+            let label = read_u32(&bytes, &mut pos)? as usize;
+            let outcome = f32::from_bits(read_u32(&bytes, &mut pos)?) as f64;
+            let mut features = Vec::with_capacity(num_features);
+            for _ in 0..num_features {
+                features.push(f32::from_bits(read_u32(&bytes, &mut pos)?) as f64);
+            }
+            let packed = bytes.get(pos..pos + mask_bytes).ok_or("Unexpected end of dataset")?;
+            let legal = (0..num_mask).map(|bit| packed[bit / 8] & (1 << (bit % 8)) != 0).collect();
+            pos += mask_bytes;
+            dataset.push_sample(Sample { features, legal, label, outcome });
+        }
+        Ok(dataset)
+    }
+}
+
+fn read_u32(bytes: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let chunk = bytes.get(*pos..*pos + 4).ok_or("Unexpected end of dataset")?;
+    *pos += 4;
+    Ok(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dataset, Sample};
+
+    fn dataset() -> Dataset {
+        let mut dataset = Dataset::new();
+        //ten mask bits, so the binary format packs them over two bytes
+        dataset.push_sample(Sample { features: vec![0.0, 1.0, 0.5, 12.0], legal: (0..10).map(|i| i % 3 == 0).collect(), label: 3, outcome: 1.0 });
+        dataset.push_sample(Sample { features: vec![2.0, 0.0, 0.25, 7.0], legal: (0..10).map(|i| i == 9).collect(), label: 9, outcome: -1.0 });
+        dataset
+    }
+
+    #[test]
+    fn csv_and_binary_files_read_back_unchanged() {
+        let dataset = dataset();
+        let dir = std::env::temp_dir();
+        let csv = dir.join(format!("coup-dataset-{}.csv", std::process::id()));
+        let bin = dir.join(format!("coup-dataset-{}.bin", std::process::id()));
+        dataset.save_csv(&csv).unwrap();
+        dataset.save_binary(&bin).unwrap();
+        let (from_csv, from_bin) = (Dataset::load_csv(&csv), Dataset::load_binary(&bin));
+        let _ = std::fs::remove_file(&csv);
+        let _ = std::fs::remove_file(&bin);
+        assert_eq!(from_csv.unwrap(), dataset);
+        assert_eq!(from_bin.unwrap(), dataset);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::action::Action;
+use crate::action_space::ActionSpace;
+use crate::game::GameState;
+use crate::gameai::GameStateAI;
+use crate::player::Player;
+use crate::probe::{advance, new_game, Advance};
+
+/// What the player about to act sees.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    /// Seat that acts next, or that acted last once the game is over.
+    pub player: usize,
+    /// `GameStateAI::features` from `player`'s point of view.
+    pub features: Vec<f64>,
+    /// Which indices of the action space are legal right now; all false once done.
+    pub legal: Vec<bool>,
+    pub state: GameStateAI,
+}
+
+/// Extra information returned by [`CoupEnv::step`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info {
+    /// Seat that took the action passed to `step`.
+    pub acted: usize,
+    /// Reward of every seat for this transition; non-zero only when the game ends.
+    pub rewards: Vec<f64>,
+    pub winner: Option<usize>,
+    /// Turns completed so far.
+    pub turns: usize,
+    /// Decisions played by the agents registered with [`CoupEnv::set_agent`] since the
+    /// previous observation.
+    pub agent_actions: Vec<(usize, Action)>,
+}
+
+/// Gym-style environment over the engine for one or more learning agents.
+///
+/// Every decision of the game, whether a turn action, block, challenge or pass, is a
+/// step. The action space is an [`ActionSpace`], so index `i` means the same move
+/// relative to whoever acts. Seats that have an agent registered play automatically;
+/// the observation always belongs to the next seat without one. The winner gets 1 and
+/// everyone else -1 at the end.
+pub struct CoupEnv {
+    num_players: usize,
+    space: ActionSpace,
+    agents: Vec<Option<Box<dyn Player>>>,
+    /// The game before the current engine step, and the decisions already taken in it.
+    game: GameState,
+    script: Vec<Action>,
+    pending: Option<(usize, GameStateAI, Vec<Action>)>,
+    done: bool,
+}
+
+impl CoupEnv {
+    pub fn new(num_players: usize) -> CoupEnv {
+        CoupEnv {
+            num_players,
+            space: ActionSpace::new(num_players),
+            agents: (0..num_players).map(|_| None).collect(),
+            game: new_game(num_players, 0),
+            script: Vec::new(),
+            pending: None,
+            done: true,
+        }
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    /// Size of the discrete action space.
+    pub fn num_actions(&self) -> usize {
+        self.space.len()
+    }
+
+    /// Length of [`Observation::features`].
+    pub fn observation_len(&self) -> usize {
+        GameStateAI::feature_len(self.num_players)
+    }
+
+    /// Lets `agent` play `seat` from now on; only its `choose_action` is used.
+    pub fn set_agent(&mut self, seat: usize, agent: Box<dyn Player>) {
+        self.agents[seat] = Some(agent);
+    }
+
+    /// The action space from `player`'s seat: index `i` is the action `i` stands for.
+    pub fn actions(&self, player: usize) -> Vec<Action> {
+        self.space.actions(player)
+    }
+
+    /// Deals a new game from `seed` and returns the first observation. Seat 0 starts.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = new_game(self.num_players, seed);
+        self.script.clear();
+        self.pending = None;
+        self.done = false;
+        self.advance(&mut Vec::new());
+        self.observation(0)
+    }
+
+    /// Plays `action_index` for the seat to act and runs the game until the next
+    /// decision for a seat without an agent, or the end.
+    pub fn step(&mut self, action_index: usize) -> Result<(Observation, f64, bool, Info), String> {
+        let (player, _, options) = match (&self.pending, self.done) {
+            (Some(pending), false) => pending.clone(),
+            _ => return Err("The game is over; call reset".to_string()),
+        };
+        let action = self.space.decode(action_index, player).ok_or_else(|| format!("Action index {} out of range", action_index))?;
+        if !options.contains(&action) {
+            return Err(format!("{} is not legal for player {}", action, player));
+        }
+
+        self.script.push(action);
+        let mut agent_actions = Vec::new();
+        self.advance(&mut agent_actions);
+
+        let winner = if self.done { self.game.winning_player() } else { None };
+        let rewards: Vec<f64> = (0..self.num_players)
+            .map(|i| match winner {
+                Some(w) if w == i => 1.0,
+                Some(_) => -1.0,
+                None => 0.0,
+            })
+            .collect();
+        let info = Info { acted: player, rewards: rewards.clone(), winner, turns: self.game.turn.number, agent_actions };
+        Ok((self.observation(player), rewards[player], self.done, info))
+    }
+
+    /// Steps the engine until a seat without an agent has to decide or the game ends.
+    fn advance(&mut self, agent_actions: &mut Vec<(usize, Action)>) {
+        self.pending = None;
+        loop {
+            if self.game.game_over() {
+                self.done = true;
+                return;
+            }
+            match advance(&self.game, &self.script) {
+                Advance::Stepped(next) => {
+                    self.game = *next;
+                    self.script.clear();
+                },
+                Advance::Decision(pending) => match &self.agents[pending.player] {
+                    Some(agent) => {
+                        let action = agent.choose_action(pending.options.clone(), &pending.observation);
+                        agent_actions.push((pending.player, action.clone()));
+                        self.script.push(action);
+                    },
+                    None => {
+                        self.pending = Some((pending.player, pending.observation, pending.options));
+                        return;
+                    },
+                },
+            }
+        }
+    }
+
+    /// The pending decision, or `last`'s view of the final position once the game is over.
+    fn observation(&self, last: usize) -> Observation {
+        match &self.pending {
+            Some((player, state, options)) => Observation {
+                player: *player,
+                features: state.features(),
+                legal: self.space.mask(options, *player),
+                state: state.clone(),
+            },
+            None => {
+                let state = GameStateAI::from_gamestate(&self.game, last);
+                Observation { player: last, features: state.features(), legal: vec![false; self.num_actions()], state }
+            },
+        }
+    }
+}
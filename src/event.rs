@@ -0,0 +1,29 @@
+use crate::action::Action;
+use crate::character::Character;
+use crate::phase::Phase;
+
+/// Something every player at the table saw happen, in the order recorded in
+/// `GameState::events`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A turn action, block or challenge.
+    Played { player: usize, action: Action },
+    /// `player` let the pending claim stand in a challenge or block window.
+    Passed { player: usize, phase: Phase },
+    /// A challenge against `claimant`'s `claim` was settled; `proven` if they held the character.
+    ChallengeResolved { claimant: usize, challenger: usize, claim: Action, proven: bool },
+    /// `player` lost an influence and turned `card` face up.
+    Revealed { player: usize, card: Character },
+    /// `player` swapped cards with the deck, so their hand is no longer known: as many as
+    /// `Ruleset::exchange_draw` for an Exchange, one for a Report or a forced exchange.
+    Exchanged { player: usize },
+    /// Setup: the players took their seats, `order[seat]` being the player's index in
+    /// the list passed to `GameState::new`.
+    Seated { order: Vec<usize> },
+    /// Setup: `player` takes the first turn.
+    FirstPlayer { player: usize },
+    /// Setup: `player` was dealt `cards` cards and `coins` coins.
+    Dealt { player: usize, cards: usize, coins: usize },
+    /// Setup: `player` drew an extra card and returned one to the deck.
+    Drafted { player: usize },
+}
@@ -0,0 +1,24 @@
+/// Allegiance of a player in the Reformation expansion.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Faction {
+    Loyalist,
+    Reformist,
+}
+
+impl Faction {
+    pub fn opposite(&self) -> Faction {
+        match self {
+            Faction::Loyalist => Faction::Reformist,
+            Faction::Reformist => Faction::Loyalist,
+        }
+    }
+}
+
+impl std::fmt::Display for Faction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Faction::Loyalist => write!(f, "Loyalist"),
+            Faction::Reformist => write!(f, "Reformist"),
+        }
+    }
+}
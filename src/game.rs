@@ -1,285 +1,926 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::SeedableRng;
 
 use crate::character::Character;
+use crate::event::Event;
+use crate::faction::Faction;
 use crate::gameai::GameStateAI;
 use crate::player::Player;
-use crate::action::{Action, self, BlockAction};
+use crate::action::Action;
 use crate::phase::Phase;
+use crate::response::{Response, ResponseWindow, TieBreak};
+use crate::result::{Elimination, EliminationCause, Ending, GameResult};
+use crate::setup::{FirstPlayer, SeatOrder, Setup};
+use crate::ruleset::Ruleset;
+use crate::team::Teams;
+
+/// A choice made by a player, as recorded when `GameState::decisions` is enabled.
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub player: usize,
+    pub observation: GameStateAI,
+    pub options: Vec<Action>,
+    pub action: Action,
+}
+
+/// Where the current turn is. Turns go Action, ChallengeAction, Block, ChallengeBlock,
+/// Resolve, skipping straight to Resolve once the action has been stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TurnStage {
+    Action,
+    ChallengeAction,
+    Block,
+    ChallengeBlock,
+    Resolve,
+}
+
+/// Public progress of the turn being played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    /// Number of turns completed before this one.
+    pub number: usize,
+    pub stage: TurnStage,
+    pub action: Option<Action>,
+    /// The block that was raised and the player who raised it.
+    pub block: Option<(Action, usize)>,
+    pub challenged: bool,
+    pub blocked: bool,
+}
+
+impl Turn {
+    pub fn new(number: usize) -> Turn {
+        Turn {
+            number,
+            stage: TurnStage::Action,
+            action: None,
+            block: None,
+            challenged: false,
+            blocked: false,
+        }
+    }
+}
 
 pub struct GameState {
     pub players: Vec<Box<dyn Player>>,
     pub deck: Vec<Character>,
     pub revealed_characters: Vec<Option<Character>>,
     pub history: Vec<(Action, usize)>,
+    /// Public record of the game, including challenge outcomes and revealed cards.
+    pub events: Vec<Event>,
     pub current_player: usize,
     pub phase: Phase,
+    pub turn: Turn,
+    pub tie_break: TieBreak,
+    /// Source of all randomness in the game (seating, shuffles, random tie-breaks).
+    /// Replace it with a seeded generator to make a game reproducible.
+    pub rng: StdRng,
+    /// Print eliminations and the winner to stdout.
+    pub verbose: bool,
+    /// Every decision taken so far, if recording was enabled by setting this to `Some`.
+    pub decisions: Option<Vec<Decision>>,
+    /// Reformation expansion: the faction of every seat, or None for the base game.
+    pub factions: Option<Vec<Faction>>,
+    /// Reformation expansion: coins paid for conversions, waiting to be embezzled.
+    pub treasury: usize,
+    /// Characters in play, their powers and the coin amounts of the actions.
+    pub rules: Ruleset,
+    /// The opponent and card the current player is looking at while deciding whether
+    /// to force an exchange after an examination. Only the examiner is shown it.
+    pub examined: Option<(usize, Character)>,
+    /// Team play: the team of every seat, or None when everyone plays for themselves.
+    pub teams: Option<Teams>,
+    /// Players knocked out so far, in order.
+    pub eliminations: Vec<Elimination>,
+    /// Number of the first turn since anyone last lost influence, for stalemate detection.
+    pub quiet_since: usize,
 }
 
 impl GameState {
     pub fn new(players: Vec<Box<dyn Player>>) -> GameState {
-        let cards_to_reveal = 15 - 2 * players.len();
-        let mut revealed_characters = Vec::new();
-        for _ in 0..cards_to_reveal {
-            revealed_characters.push(None);
-        }
-
         GameState {
             players,
-            deck: Character::create_deck(),
-            revealed_characters: revealed_characters,
+            deck: Ruleset::classic().deck(),
+            revealed_characters: Vec::new(),
             history: Vec::new(),
+            events: Vec::new(),
             current_player: 0,
             phase: Phase::Action,
+            turn: Turn::new(0),
+            tie_break: TieBreak::default(),
+            rng: StdRng::from_entropy(),
+            verbose: true,
+            decisions: None,
+            factions: None,
+            treasury: 0,
+            rules: Ruleset::classic(),
+            examined: None,
+            teams: None,
+            eliminations: Vec::new(),
+            quiet_since: 0,
+        }
+    }
+
+    /// Plays in teams, `teams.team[i]` being the team of `players[i]` as passed to
+    /// [`GameState::new`]. Seats keep their team when `start_game` shuffles them.
+    pub fn with_teams(mut self, teams: Teams) -> Self {
+        self.teams = Some(teams);
+        self
+    }
+
+    /// Plays with the Reformation expansion: factions alternate around the table,
+    /// starting with a Loyalist in seat 0.
+    pub fn enable_reformation(&mut self) {
+        self.factions = Some((0..self.players.len()).map(|i| if i % 2 == 0 { Faction::Loyalist } else { Faction::Reformist }).collect());
+        self.treasury = 0;
+    }
+
+    /// Plays by `rules`. Replaces the deck, so it must be called before dealing.
+    pub fn set_rules(&mut self, rules: Ruleset) {
+        self.deck = rules.deck();
+        self.rules = rules;
+    }
+
+    /// Plays with the Inquisitor in place of the Ambassador. Must be called before dealing.
+    pub fn enable_inquisitor(&mut self) {
+        self.set_rules(Ruleset::inquisitor());
+    }
+
+    /// Whether `player` may attack `target` (Coup, Assassinate, Steal) or block their
+    /// Foreign Aid. In Reformation games players must leave their own faction alone as
+    /// long as someone of the other faction is still in the game. In team games
+    /// teammates leave each other alone unless friendly fire is on.
+    pub fn may_target(&self, player: usize, target: usize) -> bool {
+        if let Some(teams) = &self.teams {
+            if !teams.friendly_fire && teams.same_team(player, target) {
+                return false;
+            }
+        }
+        match &self.factions {
+            Some(factions) => {
+                factions[player] != factions[target]
+                    || !self.alive_players().iter().any(|&i| factions[i] != factions[player])
+            },
+            None => true,
         }
     }
 
     pub fn next_player(&mut self) {
         self.current_player = (self.current_player + 1) % self.players.len();
+        while self.is_eliminated(self.current_player) {
+            self.current_player = (self.current_player + 1) % self.players.len();
+        }
     }
 
+    /// Resolves a challenge against the claim behind `action`. Returns true if the
+    /// challenge succeeded, i.e. the claimant did not hold the character.
     pub fn resolve_challenge(&mut self, action: Action, challenger: usize, blocker: Option<usize>) -> bool {
-        //is the card for the responsible action in the player's hand?
-        let challenge_successful = match action {
-            Action::Tax => {
-                //if player has duke, challenge is successful
-                self.players[self.current_player].hand().contains(&Character::Duke)
-            },
-            Action::Assassinate(_) => {
-                //if player has assassin, challenge is successful
-                self.players[self.current_player].hand().contains(&Character::Assassin)
-            },
-            Action::Steal(_, _) => {
-                //if player has captain, challenge is successful
-                self.players[self.current_player].hand().contains(&Character::Captain)
-            },
-            Action::Exchange => {
-                //if player has ambassador, challenge is successful
-                self.players[self.current_player].hand().contains(&Character::Ambassador)
-            },
-            Action::Block(block_action, _, _) => {
-                let blocker = blocker.unwrap();
-                match block_action {
-                    BlockAction::ForeignAid(_) => {
-                        //if player has duke, challenge is successful
-                        self.players[blocker].hand().contains(&Character::Duke)
-                    },
-                    action::BlockAction::Assassinate(_) => {
-                        //if player has contessa, challenge is successful
-                        self.players[blocker].hand().contains(&Character::Contessa)
-                    },
-                    action::BlockAction::Stealing(_, _) => {
-                        //if player has captain or ambassador, challenge is successful
-                        self.players[blocker].hand().contains(&Character::Captain) || self.players[blocker].hand().contains(&Character::Ambassador)
-                    },
-                }
-            },
-            _ => {true},
-        };
+        let claimant = blocker.unwrap_or(self.current_player);
+        //does the claimant's hand back the claim under the rules in play?
+        let hand = self.players[claimant].hand();
+        let claim_holds = self.rules.proves(&hand, &action);
 
-        if challenge_successful {
-            //if challenge is successful, the challenger loses an influence
-            let card = self.players[challenger].choose_card(&self);
-            let _ = self.players[challenger].remove_card_from_hand(card);
-            self.check_elimination(challenger)
+        self.events.push(Event::ChallengeResolved { claimant, challenger, claim: action, proven: claim_holds });
+        if claim_holds {
+            //the challenger loses an influence
+            self.lose_influence(challenger, claimant, EliminationCause::FailedChallenge);
         } else {
-            if blocker.is_none() {
-                //if challenge is unsuccessful, the player loses an influence
-                let card = self.players[self.current_player].choose_card(&self);
-                let _ = self.players[self.current_player].remove_card_from_hand(card);
-                self.check_elimination(self.current_player)
-            } else {
-                //if challenge is unsuccessful, the blocker loses an influence
-                let card = self.players[blocker.unwrap()].choose_card(&self);
-                let _ = self.players[blocker.unwrap()].remove_card_from_hand(card);
-                self.check_elimination(blocker.unwrap())
-            }
+            //the claimant was bluffing and loses an influence
+            self.lose_influence(claimant, challenger, EliminationCause::FailedBluff);
         }
 
-        true
+        !claim_holds
     }
 
-    pub fn check_elimination(&mut self, index: usize) {
-        if self.players[index].hand().len() == 0 {
-            println!("Player {} eliminated!", self.players[index].name());
-            self.players.remove(index);
-            if self.current_player >= index {
-                self.current_player -= 1;
-            }
+    /// Makes the player reveal and give up one card of their choice because of `by`'s
+    /// move. Does nothing if they are already out.
+    pub fn lose_influence(&mut self, index: usize, by: usize, cause: EliminationCause) {
+        if self.is_eliminated(index) {
+            return;
+        }
+        let card = self.players[index].choose_card(self);
+        if self.players[index].remove_card_from_hand(card).is_ok() {
+            self.quiet_since = self.turn.number + 1;
+            self.revealed_characters.push(Some(card));
+            self.events.push(Event::Revealed { player: index, card });
         }
+        self.check_elimination(index, by, cause);
     }
 
-    pub fn game_over(&self) -> bool {
-        self.players.len() == 1
+    /// Records `index` as knocked out by `by` if they have no influence left.
+    pub fn check_elimination(&mut self, index: usize, by: usize, cause: EliminationCause) {
+        if !self.is_eliminated(index) || self.eliminations.iter().any(|e| e.player == index) {
+            return;
+        }
+        self.eliminations.push(Elimination { player: index, turn: self.turn.number + 1, by, cause });
+        if self.verbose {
+            println!("Player {} eliminated by {} ({})!", self.players[index].name(), self.players[by].name(), cause);
+        }
     }
 
-    pub fn winner(&self) -> Option<&Box<dyn Player>> {
-        if self.game_over() {
-            Some(&self.players[0])
+    /// Eliminated players keep their seat so that indices stay stable for the whole game.
+    pub fn is_eliminated(&self, index: usize) -> bool {
+        self.players[index].hand().is_empty()
+    }
+
+    pub fn alive_players(&self) -> Vec<usize> {
+        (0..self.players.len()).filter(|&i| !self.is_eliminated(i)).collect()
+    }
+
+    /// Why the game is over, or None while it goes on: one player (or team) is left,
+    /// the turn limit was reached or nobody lost influence for too long.
+    pub fn ending(&self) -> Option<Ending> {
+        let alive = self.alive_players();
+        let last_standing = match &self.teams {
+            Some(teams) => alive.iter().all(|&i| teams.same_team(i, alive[0])),
+            None => alive.len() == 1,
+        };
+        let config = &self.rules.config;
+        if last_standing {
+            Some(Ending::LastStanding)
+        } else if config.max_turns.is_some_and(|max| self.turn.number >= max) {
+            Some(Ending::TurnLimit)
+        } else if config.stalemate_rounds.is_some_and(|rounds| self.turn.number >= self.quiet_since + rounds * alive.len()) {
+            Some(Ending::Stalemate)
         } else {
             None
         }
     }
 
+    pub fn game_over(&self) -> bool {
+        self.ending().is_some()
+    }
+
+    /// Influence and coins of the side `player` is on: their team, or just them.
+    fn standing(&self, player: usize) -> (usize, usize) {
+        let side: Vec<usize> = match &self.teams {
+            Some(teams) => self.alive_players().into_iter().filter(|&i| teams.same_team(i, player)).collect(),
+            None => vec![player],
+        };
+        (side.iter().map(|&i| self.players[i].hand().len()).sum(), side.iter().map(|&i| self.players[i].coins()).sum())
+    }
+
+    /// Seat of the winner once the game is over: the last player standing, or the one
+    /// with most influence, then most coins, when the game was stopped. In team games,
+    /// the first surviving member of the winning team. None while the game goes on and
+    /// for a draw.
+    pub fn winning_player(&self) -> Option<usize> {
+        let ending = self.ending()?;
+        let alive = self.alive_players();
+        if ending == Ending::LastStanding {
+            return alive.first().copied();
+        }
+        let best = alive.iter().map(|&i| self.standing(i)).max()?;
+        let leaders: Vec<usize> = alive.into_iter().filter(|&i| self.standing(i) == best).collect();
+        let one_side = match &self.teams {
+            Some(teams) => leaders.iter().all(|&i| teams.same_team(i, leaders[0])),
+            None => leaders.len() == 1,
+        };
+        if one_side { Some(leaders[0]) } else { None }
+    }
+
+    pub fn winner(&self) -> Option<&dyn Player> {
+        self.winning_player().map(|i| self.players[i].as_ref())
+    }
+
+    /// The team that won a team game.
+    pub fn winning_team(&self) -> Option<usize> {
+        let teams = self.teams.as_ref()?;
+        self.winning_player().map(|i| teams.team[i])
+    }
+
+    /// The placements and eliminations of the game, once it is over.
+    pub fn result(&self) -> Option<GameResult> {
+        let ending = self.ending()?;
+        let winner = self.winning_player();
+        let mut placements = self.alive_players();
+        // Stable, so survivors of a finished game and tied players keep seat order.
+        placements.sort_by_key(|&i| std::cmp::Reverse((Some(i) == winner, self.standing(i), self.players[i].hand().len(), self.players[i].coins())));
+        placements.extend(self.eliminations.iter().rev().map(|e| e.player));
+        Some(GameResult {
+            ending,
+            winner,
+            winning_team: self.winning_team(),
+            placements,
+            eliminations: self.eliminations.clone(),
+            // A game can end in a challenge before its last turn resolves.
+            turns: self.turn.number.max(self.eliminations.last().map_or(0, |e| e.turn)),
+        })
+    }
+
+    /// Whether `player` won: they are the winner, or their team is.
+    pub fn has_won(&self, player: usize) -> bool {
+        match &self.teams {
+            Some(teams) => self.winning_team() == Some(teams.team[player]),
+            None => self.winning_player() == Some(player),
+        }
+    }
+
+    /// Asks `player` to pick one of `options`, recording the decision if requested.
+    pub fn decide(&mut self, player: usize, options: Vec<Action>) -> Action {
+        let observation = GameStateAI::from_gamestate(self, player);
+        let action = self.players[player].choose_action(options.clone(), &observation);
+        if let Some(decisions) = self.decisions.as_mut() {
+            decisions.push(Decision { player, observation, options, action: action.clone() });
+        }
+        action
+    }
+
+    /// Asks every responder for a pass/challenge/block without showing them each other's
+    /// answers, then records the passes and the response picked by `tie_break`.
+    pub fn response_window(&mut self, phase: Phase, responders: &[usize], claimant: usize) -> Option<Response> {
+        self.phase = phase;
+        let mut window = ResponseWindow::new();
+        for &i in responders {
+            let mut options = self.players[i].possible_actions(self, i);
+            if options.is_empty() {
+                continue;
+            }
+            options.push(Action::Pass);
+            let response = self.decide(i, options);
+            window.submit(i, response);
+        }
+
+        let selected = window.resolve(claimant, self.players.len(), self.tie_break, &mut self.rng).cloned();
+        for pass in window.passes() {
+            self.history.push((pass.action.clone(), pass.player));
+            self.events.push(Event::Passed { player: pass.player, phase });
+        }
+        if let Some(response) = &selected {
+            self.history.push((response.action.clone(), response.player));
+            self.events.push(Event::Played { player: response.player, action: response.action.clone() });
+        }
+        selected
+    }
+
     pub fn run(&mut self) {
         while !self.game_over() {
-            let current_player = self.current_player;
+            self.step();
+        }
+        //announce winner
+        if self.verbose {
+            if let Some(ending @ (Ending::TurnLimit | Ending::Stalemate)) = self.ending() {
+                println!("{} reached, going to the tiebreak.", if ending == Ending::TurnLimit { "Turn limit" } else { "Stalemate" });
+            }
+            match (self.winning_team(), self.winner()) {
+                (Some(team), _) => {
+                    let names: Vec<String> = self.teams.as_ref().unwrap().members(team).iter().map(|&i| self.players[i].name()).collect();
+                    println!("Team {} ({}) wins!", team, names.join(", "));
+                },
+                (None, Some(winner)) => println!("{} wins!", winner.name()),
+                (None, None) => println!("The game is a draw."),
+            }
+        }
+    }
 
-            // ACTION PHASE
-            self.phase = Phase::Action;
-            let actions = self.players[current_player].possible_actions(self);
-            let action = self.players[current_player].choose_action(actions, &GameStateAI::from_gamestate(self, self.players[current_player].name()));
-            self.history.push((action.clone(), current_player));
-            let mut challenged = false;
-            let mut blocked = false;
+    /// Plays the rest of the current turn.
+    pub fn play_turn(&mut self) {
+        let turn_start = self.turn.number;
+        while !self.game_over() && self.turn.number == turn_start {
+            self.step();
+        }
+    }
+
+    /// Advances the game by one stage of the current turn. Every stage asks for at most
+    /// one decision or one response window, so the game can be paused and inspected
+    /// between steps.
+    pub fn step(&mut self) {
+        if self.game_over() {
+            return;
+        }
+        let current_player = self.current_player;
 
+        match self.turn.stage {
+            // ACTION PHASE
+            TurnStage::Action => {
+                self.phase = Phase::Action;
+                let actions = self.players[current_player].possible_actions(self, current_player);
+                let action = self.decide(current_player, actions);
+                self.history.push((action.clone(), current_player));
+                self.events.push(Event::Played { player: current_player, action: action.clone() });
+                self.turn.action = Some(action);
+                self.turn.stage = TurnStage::ChallengeAction;
+            },
             // CHALLENGE PHASE
-            self.phase = Phase::Challenge;
-            //loop all players except current player
-            for i in 0..self.players.len() {
-                if i != current_player {
-                    let mut challenges = self.players[i].possible_actions(self);
-                    if challenges.len() == 0 {
-                        continue;
-                    }
-                    challenges.push(Action::Pass);
-                    let challenge = self.players[i].choose_action(challenges, &GameStateAI::from_gamestate(self, self.players[current_player].name()));
-                    self.history.push((challenge.clone(), i));
-                    
-                    // if Pass was picked, continue
-                    if let Action::Pass = challenge {
-                        continue;
-                    }
-                    challenged = self.resolve_challenge(action.clone(), i, None);
-                    if challenged {
-                        break;
-                    }
+            TurnStage::ChallengeAction => {
+                let opponents: Vec<usize> = self.alive_players().into_iter().filter(|&i| i != current_player).collect();
+                if let Some(challenge) = self.response_window(Phase::Challenge, &opponents, current_player) {
+                    let action = self.turn.action.clone().expect("challenge stage without an action");
+                    self.turn.challenged = self.resolve_challenge(action, challenge.player, None);
                 }
-            }
+                self.turn.stage = if self.turn.challenged || self.is_eliminated(current_player) {
+                    TurnStage::Resolve
+                } else {
+                    TurnStage::Block
+                };
+            },
             // BLOCK PHASE
-            if !challenged {
-                //loop all players except current player
-                for i in 0..self.players.len() {
-                    if i != current_player {
-                        let mut block_challenged = false;
-                        self.phase = Phase::Block;
-                        let mut blocks = self.players[i].possible_actions(self);
-                        println!("Player {} has {} blocks", i, blocks.len());
-                        if blocks.len() == 0 {
-                            continue;
-                        }
-                        blocks.push(Action::Pass);
-                        let block = self.players[i].choose_action(blocks, &GameStateAI::from_gamestate(self, self.players[current_player].name()));
-                        self.history.push((block.clone(), i));
-                        
-                        // if Pass was picked, continue
-                        if let Action::Pass = block {
-                            continue;
-                        }
-
-                        //CHALLENGE PHASE
-                        self.phase = Phase::Challenge;
-                        //loop all players except player who blocked
-                        for j in 0..self.players.len() {
-                            if j != i {
-                                let mut challenges = self.players[j].possible_actions(self);
-                                if challenges.len() == 0 {
-                                    continue;
-                                }
-                                challenges.push(Action::Pass);
-                                let challenge = self.players[j].choose_action(challenges, &GameStateAI::from_gamestate(self, self.players[current_player].name()));
-                                self.history.push((challenge.clone(), j));
-                                
-                                // if Pass was picked, continue
-                                if let Action::Pass = challenge {
-                                    continue;
-                                }
-                                block_challenged = self.resolve_challenge(block.clone(), j, Some(i));
-                                if block_challenged {
-                                    break;
-                                }
-                            }
-                        }
-
-                        if !block_challenged {
-                            blocked = true;
-                        }
-                    }
+            TurnStage::Block => {
+                let opponents: Vec<usize> = self.alive_players().into_iter().filter(|&i| i != current_player).collect();
+                match self.response_window(Phase::Block, &opponents, current_player) {
+                    Some(block) => {
+                        self.turn.block = Some((block.action, block.player));
+                        self.turn.stage = TurnStage::ChallengeBlock;
+                    },
+                    None => self.turn.stage = TurnStage::Resolve,
                 }
-            }
+            },
+            //CHALLENGE PHASE
+            TurnStage::ChallengeBlock => {
+                let (block, blocker) = self.turn.block.clone().expect("block challenge stage without a block");
+                let others: Vec<usize> = self.alive_players().into_iter().filter(|&j| j != blocker).collect();
+                let block_challenged = match self.response_window(Phase::Challenge, &others, blocker) {
+                    Some(challenge) => self.resolve_challenge(block, challenge.player, Some(blocker)),
+                    None => false,
+                };
+                self.turn.blocked = !block_challenged;
+                self.turn.stage = TurnStage::Resolve;
+            },
+            TurnStage::Resolve => {
+                if !self.turn.challenged && !self.turn.blocked && !self.is_eliminated(current_player) {
+                    let action = self.turn.action.clone().expect("resolve stage without an action");
+                    self.resolve_action(action);
+                }
+                self.turn = Turn::new(self.turn.number + 1);
+                if !self.game_over() {
+                    self.next_player();
+                }
+            },
+        }
+    }
 
-            if !challenged && !blocked {
-                match action {
-                    Action::Income => {
+    /// Carries out an action that was neither successfully challenged nor blocked.
+    fn resolve_action(&mut self, action: Action) {
+        let current_player = self.current_player;
+        match action {
+            Action::Income => {
+                self.players[current_player].add_coins(self.rules.config.income);
+            },
+            Action::ForeignAid => {
+                self.players[current_player].add_coins(self.rules.config.foreign_aid);
+            },
+            Action::Coup(target) => {
+                let _ = self.players[current_player].lose_coins(self.rules.config.coup_cost);
+                self.lose_influence(target, current_player, EliminationCause::Coup);
+            },
+            Action::Tax => {
+                self.players[current_player].add_coins(self.rules.config.tax);
+            },
+            Action::Assassinate(target) => {
+                let _ = self.players[current_player].lose_coins(self.rules.config.assassinate_cost);
+                self.lose_influence(target, current_player, EliminationCause::Assassination);
+            },
+            Action::Exchange => {
+                //draw from the deck, then return as many cards
+                let mut drawn = 0;
+                for _ in 0..self.rules.exchange_draw {
+                    if let Some(card) = self.deck.pop() {
+                        self.players[current_player].add_card_to_hand(card);
+                        drawn += 1;
+                    }
+                }
+                let mut returned = Vec::new();
+                for _ in 0..drawn {
+                    let card = self.players[current_player].choose_return(self);
+                    let _ = self.players[current_player].remove_card_from_hand(card);
+                    returned.push(card);
+                }
+                self.deck.extend(returned);
+                self.deck.shuffle(&mut self.rng);
+                self.events.push(Event::Exchanged { player: current_player });
+            },
+            Action::Steal(target, amount) => {
+                self.players[current_player].add_coins(amount);
+                let _ = self.players[target].lose_coins(amount);
+            },
+            Action::Convert(target) => {
+                let cost = if target == current_player { 1 } else { 2 };
+                if self.players[current_player].lose_coins(cost).is_ok() {
+                    self.treasury += cost;
+                    if let Some(factions) = self.factions.as_mut() {
+                        factions[target] = factions[target].opposite();
+                    }
+                }
+            },
+            Action::Embezzle => {
+                self.players[current_player].add_coins(self.treasury);
+                self.treasury = 0;
+            },
+            Action::Redistribute => {
+                let coins = self.players[current_player].coins();
+                for i in self.alive_players() {
+                    if i != current_player && self.players[i].coins() > coins {
+                        let _ = self.players[i].lose_coins(1);
                         self.players[current_player].add_coins(1);
-                    },
-                    Action::ForeignAid => {
-                        self.players[current_player].add_coins(2);
-                    },
-                    Action::Coup(target) => {
-                        self.players[current_player].lose_coins(7);
-                        let card = self.players[target].choose_card(self);
-                        self.players[target].remove_card_from_hand(card);
-                        self.check_elimination(target);
-                    },
-                    Action::Tax => {
-                        self.players[current_player].add_coins(3);
-                    },
-                    Action::Assassinate(target) => {
-                        self.players[current_player].lose_coins(3);
-                        let card = self.players[target].choose_card(self);
-                        self.players[target].remove_card_from_hand(card);
-                        self.check_elimination(target);
-                    },
-                    Action::Exchange => {
-                        let card1 = self.deck.pop().unwrap();
-                        let card2 = self.deck.pop().unwrap();
-                        self.players[current_player].add_card_to_hand(card1);
-                        self.players[current_player].add_card_to_hand(card2);
-                        let card1 = self.players[current_player].choose_card(self);
-                        let card2 = self.players[current_player].choose_card(self);
-                        self.players[current_player].remove_card_from_hand(card1);
-                        self.players[current_player].remove_card_from_hand(card2);
-                        self.deck.shuffle(&mut rand::thread_rng());
-                    },
-                    Action::Steal(target, amount) => {
-                        self.players[current_player].add_coins(amount);
-                        self.players[target].lose_coins(amount);
-                    },
-                    _ => {},
+                    }
                 }
-            }
-
-            // check if game is over
-            if self.game_over() {
-                //announce winner
-                println!("{} wins!", self.winner().unwrap().name());
-            } else {
-                self.next_player();
-            }
+            },
+            Action::Speculate => {
+                let coins = self.players[current_player].coins().min(self.rules.config.tax);
+                self.players[current_player].add_coins(coins);
+            },
+            Action::Report => {
+                self.players[current_player].add_coins(self.rules.config.income);
+                if let Some(card) = self.deck.pop() {
+                    self.players[current_player].add_card_to_hand(card);
+                    let returned = self.players[current_player].choose_return(self);
+                    let _ = self.players[current_player].remove_card_from_hand(returned);
+                    self.deck.push(returned);
+                    self.deck.shuffle(&mut self.rng);
+                    self.events.push(Event::Exchanged { player: current_player });
+                }
+            },
+            Action::Give(target, coins) if self.players[current_player].coins() >= coins => {
+                let _ = self.players[current_player].lose_coins(coins);
+                self.players[target].add_coins(coins);
+            },
+            Action::Examine(target) if !self.is_eliminated(target) => {
+                //the target picks the card to show
+                let card = self.players[target].choose_card(self);
+                self.examined = Some((target, card));
+                let choice = self.decide(current_player, vec![Action::Pass, Action::ForceExchange(target)]);
+                self.examined = None;
+                if choice == Action::ForceExchange(target) && self.players[target].remove_card_from_hand(card).is_ok() {
+                    self.history.push((choice, current_player));
+                    self.deck.push(card);
+                    self.deck.shuffle(&mut self.rng);
+                    let drawn = self.deck.pop().unwrap();
+                    self.players[target].add_card_to_hand(drawn);
+                    self.events.push(Event::Exchanged { player: target });
+                }
+            },
+            _ => {},
         }
     }
 }
 
+/// Shuffles the seats, deals and lets seat 0 start.
 pub fn start_game(state: &mut GameState) {
-    state.players.shuffle(&mut rand::thread_rng());
+    start_game_with(state, &Setup::default()).expect("the deck is too small to deal every hand");
+}
+
+/// The pre-game setup: seats the players, picks the first player, deals and runs the
+/// optional draft. Every step is recorded in `state.events`, from which
+/// [`replay_setup`] can set up the same table again.
+pub fn start_game_with(state: &mut GameState, setup: &Setup) -> Result<(), String> {
+    let n = state.players.len();
+    state.rules.check_players(n)?;
+    if let FirstPlayer::Seat(seat) = setup.first_player {
+        if seat >= n {
+            return Err(format!("There is no seat {} at a table of {}", seat, n));
+        }
+    }
+
+    // Shuffling the seat numbers draws the same permutation as shuffling the players,
+    // and lets the teams follow their players.
+    let mut seats: Vec<usize> = (0..n).collect();
+    if setup.seating == SeatOrder::Random {
+        seats.shuffle(&mut state.rng);
+    }
+    seat(state, seats);
+
+    let first = match setup.first_player {
+        FirstPlayer::Seat(seat) => seat,
+        FirstPlayer::Random => state.rng.gen_range(0..n),
+    };
+    state.current_player = first;
+    state.events.push(Event::FirstPlayer { player: first });
+
+    deal(state);
+    if setup.two_player_rule && n == 2 {
+        let coins = state.players[first].coins();
+        let _ = state.players[first].lose_coins(coins);
+        state.players[first].add_coins(1);
+    }
+    for (player, p) in state.players.iter().enumerate() {
+        state.events.push(Event::Dealt { player, cards: p.hand().len(), coins: p.coins() });
+    }
+
+    if setup.draft {
+        //in turn order, draw one more card and put one back
+        for player in (0..n).map(|i| (first + i) % n) {
+            draft(state, player)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets up `state` the way `events` say an earlier game was set up: the players, given
+/// in the same order as to that game, take the recorded seats, the recorded first
+/// player starts, everyone gets the recorded coins and the recorded players draft.
+/// The events do not say which cards were dealt, so hands come from `state.deck`.
+pub fn replay_setup(state: &mut GameState, events: &[Event]) -> Result<(), String> {
+    let n = state.players.len();
+    let order = events.iter().find_map(|e| match e {
+        Event::Seated { order } => Some(order.clone()),
+        _ => None,
+    }).ok_or("The events do not record a seating")?;
+    let mut sorted = order.clone();
+    sorted.sort();
+    if sorted != (0..n).collect::<Vec<usize>>() {
+        return Err(format!("The recorded seating {:?} does not fit a table of {}", order, n));
+    }
+    let first = events.iter().find_map(|e| match e {
+        Event::FirstPlayer { player } => Some(*player),
+        _ => None,
+    }).filter(|&player| player < n).ok_or("The events do not record a first player")?;
+
+    seat(state, order);
+    state.current_player = first;
+    state.events.push(Event::FirstPlayer { player: first });
+    deal(state);
+    for event in events {
+        match *event {
+            Event::Dealt { player, cards, coins } if player < n => {
+                if cards != state.players[player].hand().len() {
+                    return Err(format!("Player {} was dealt {} cards, the rules deal {}", player, cards, state.players[player].hand().len()));
+                }
+                let dealt = state.players[player].coins();
+                let _ = state.players[player].lose_coins(dealt);
+                state.players[player].add_coins(coins);
+                state.events.push(Event::Dealt { player, cards, coins });
+            },
+            Event::Drafted { player } if player < n => draft(state, player)?,
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+/// Moves the players to their seats, `seats[i]` being the player to sit in seat i.
+fn seat(state: &mut GameState, seats: Vec<usize>) {
+    let mut players: Vec<Option<Box<dyn Player>>> = state.players.drain(..).map(Some).collect();
+    state.players = seats.iter().map(|&i| players[i].take().unwrap()).collect();
+    if let Some(teams) = state.teams.as_mut() {
+        teams.team = seats.iter().map(|&i| teams.team[i]).collect();
+    }
+    state.events.push(Event::Seated { order: seats });
+}
+
+/// `player` draws one more card and puts one back.
+fn draft(state: &mut GameState, player: usize) -> Result<(), String> {
+    let card = state.deck.pop().ok_or("The deck ran out during the draft")?;
+    state.players[player].add_card_to_hand(card);
+    let returned = state.players[player].choose_return(state);
+    let _ = state.players[player].remove_card_from_hand(returned);
+    state.deck.push(returned);
+    state.deck.shuffle(&mut state.rng);
+    state.events.push(Event::Drafted { player });
+    Ok(())
+}
 
-    state.deck.shuffle(&mut rand::thread_rng());
+/// Shuffles the deck and deals the opening hands, keeping the seating as given.
+pub fn deal(state: &mut GameState) {
+    state.deck.shuffle(&mut state.rng);
 
-    // Distribute two cards to each player
+    // Give every player their starting coins and influence
+    let config = &state.rules.config;
     for player in state.players.iter_mut() {
-        let card1 = state.deck.pop().unwrap();
-        let card2 = state.deck.pop().unwrap();
-        player.add_card_to_hand(card1);
-        player.add_card_to_hand(card2);
+        let _ = player.lose_coins(player.coins());
+        player.add_coins(config.starting_coins);
+        for _ in 0..config.influence {
+            let card = state.deck.pop().expect("the deck is too small to deal every hand");
+            player.add_card_to_hand(card);
+        }
     }
 }
 
 impl std::fmt::Display for GameState {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let mut s = String::new();
-        for (_i, player) in self.players.iter().enumerate() {
-            s.push_str(&format!("{} has {} coins and {:?} cards in hand. ", player.name(), player.coins(), player.hand()));
+        for (i, player) in self.players.iter().enumerate() {
+            s.push_str(&format!("{} has {} coins and {:?} cards in hand", player.name(), player.coins(), player.hand()));
+            if let Some(factions) = &self.factions {
+                s.push_str(&format!(" ({})", factions[i]));
+            }
+            if let Some(teams) = &self.teams {
+                s.push_str(&format!(" (team {})", teams.team[i]));
+            }
+            s.push_str(". ");
+        }
+        if self.factions.is_some() {
+            s.push_str(&format!("Treasury Reserve: {} coins.", self.treasury));
         }
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{replay_setup, start_game, start_game_with, GameState};
+    use crate::action::{Action, ChallengeAction};
+    use crate::character::Character;
+    use crate::config::STALEMATE_ROUNDS;
+    use crate::event::Event;
+    use crate::faction::Faction;
+    use crate::player::Player;
+    use crate::player::random::RandomPlayer;
+    use crate::player::scripted::ScriptedPlayer;
+    use crate::result::{EliminationCause, Ending};
+    use crate::ruleset::Ruleset;
+    use crate::setup::{FirstPlayer, SeatOrder, Setup};
+    use crate::team::Teams;
+
+    /// A scripted player already holding `hand` and `coins`.
+    fn seat(actions: Vec<Action>, cards: Vec<Character>, hand: &[Character], coins: usize) -> Box<dyn Player> {
+        let mut player = ScriptedPlayer::new("scripted", actions, cards);
+        for &card in hand {
+            player.add_card_to_hand(card);
+        }
+        let _ = player.lose_coins(player.coins());
+        player.add_coins(coins);
+        Box::new(player)
+    }
+
+    fn table(players: Vec<Box<dyn Player>>) -> GameState {
+        let mut game = GameState::new(players);
+        game.verbose = false;
+        game.rng = StdRng::seed_from_u64(0);
+        game
+    }
+
+    #[test]
+    fn scripted_players_replay_their_script() {
+        let mut game = table(vec![
+            seat(vec![Action::Coup(1)], vec![], &[Character::Duke, Character::Duke], 7),
+            seat(vec![], vec![Character::Assassin], &[Character::Contessa, Character::Assassin], 2),
+        ]);
+        game.play_turn();
+        assert!(game.events.contains(&Event::Played { player: 0, action: Action::Coup(1) }));
+        assert!(game.events.contains(&Event::Revealed { player: 1, card: Character::Assassin }));
+        assert_eq!((game.players[0].coins(), game.players[1].hand()), (0, vec![Character::Contessa]));
+    }
+
+    #[test]
+    fn random_players_replay_with_the_same_seeds() {
+        let play = || {
+            let mut game = table((0..3).map(|i| Box::new(RandomPlayer::new("random", i)) as Box<dyn Player>).collect());
+            start_game(&mut game);
+            game.run();
+            game.events
+        };
+        let events = play();
+        assert!(events.len() > 3);
+        assert_eq!(events, play());
+    }
+
+    /// Plays one Embezzle against a treasury of 3 that seat 1 challenges.
+    fn challenged_embezzle(hand: &[Character]) -> GameState {
+        let mut game = table(vec![
+            seat(vec![Action::Embezzle], vec![hand[0]], hand, 2),
+            seat(vec![Action::Challenge(ChallengeAction::Embezzle, 0)], vec![Character::Contessa], &[Character::Contessa, Character::Assassin], 2),
+        ]);
+        game.enable_reformation();
+        game.treasury = 3;
+        game.play_turn();
+        game
+    }
+
+    #[test]
+    fn embezzle_is_disproved_by_a_duke() {
+        let game = challenged_embezzle(&[Character::Captain, Character::Duke]);
+        assert!(game.events.contains(&Event::ChallengeResolved { claimant: 0, challenger: 1, claim: Action::Embezzle, proven: false }));
+        assert_eq!(game.players[0].hand(), vec![Character::Duke]);
+        assert_eq!(game.players[1].hand().len(), 2);
+        assert_eq!((game.players[0].coins(), game.treasury), (2, 3));
+
+        let game = challenged_embezzle(&[Character::Captain, Character::Contessa]);
+        assert!(game.events.contains(&Event::ChallengeResolved { claimant: 0, challenger: 1, claim: Action::Embezzle, proven: true }));
+        assert_eq!(game.players[0].hand().len(), 2);
+        assert_eq!(game.players[1].hand(), vec![Character::Assassin]);
+        assert_eq!((game.players[0].coins(), game.treasury), (5, 0));
+    }
+
+    #[test]
+    fn conversions_are_paid_into_the_treasury() {
+        let hand = [Character::Captain, Character::Contessa];
+        let mut game = table(vec![
+            seat(vec![Action::Convert(0)], vec![], &hand, 4),
+            seat(vec![Action::Convert(2)], vec![], &hand, 2),
+            seat(vec![Action::Embezzle], vec![], &hand, 0),
+        ]);
+        game.enable_reformation();
+        game.play_turn();
+        assert_eq!((game.players[0].coins(), game.treasury), (3, 1));
+        assert_eq!(game.factions.as_ref().unwrap()[0], Faction::Reformist);
+        game.play_turn();
+        assert_eq!((game.players[1].coins(), game.treasury), (0, 3));
+        assert_eq!(game.factions.as_ref().unwrap()[2], Faction::Reformist);
+        game.play_turn();
+        assert_eq!((game.players[2].coins(), game.treasury), (3, 0));
+    }
+
+    #[test]
+    fn teammates_are_off_limits_without_friendly_fire() {
+        let players = || (0..4).map(|_| seat(vec![], vec![], &[Character::Duke, Character::Duke], 7)).collect();
+        let coups = |game: &GameState| -> Vec<Action> {
+            game.players[0].possible_actions(game, 0).into_iter().filter(|a| matches!(a, Action::Coup(_))).collect()
+        };
+
+        let game = table(players()).with_teams(Teams::alternating(4, 2));
+        assert!(!game.may_target(0, 2) && game.may_target(0, 1));
+        assert_eq!(coups(&game), vec![Action::Coup(1), Action::Coup(3)]);
+
+        let game = table(players()).with_teams(Teams::alternating(4, 2).with_friendly_fire());
+        assert!(game.may_target(0, 2));
+        assert_eq!(coups(&game), vec![Action::Coup(1), Action::Coup(2), Action::Coup(3)]);
+    }
+
+    #[test]
+    fn result_ranks_survivors_before_the_eliminated() {
+        let mut game = table(vec![
+            seat(vec![Action::Coup(1), Action::Challenge(ChallengeAction::Tax, 2)], vec![], &[Character::Duke, Character::Duke], 7),
+            seat(vec![], vec![], &[Character::Captain], 2),
+            seat(vec![Action::Tax], vec![], &[Character::Captain], 2),
+        ]);
+        game.run();
+        let result = game.result().unwrap();
+        assert_eq!((result.ending, result.winner, result.turns), (Ending::LastStanding, Some(0), 2));
+        assert_eq!(result.placements, vec![0, 2, 1]);
+        let causes: Vec<(usize, usize, usize, EliminationCause)> = result.eliminations.iter().map(|e| (e.player, e.turn, e.by, e.cause)).collect();
+        assert_eq!(causes, vec![(1, 1, 0, EliminationCause::Coup), (2, 2, 0, EliminationCause::FailedBluff)]);
+    }
+
+    #[test]
+    fn stalemate_counts_rounds_from_the_last_loss() {
+        let hand = [Character::Captain, Character::Contessa];
+        let mut game = table(vec![
+            seat(vec![Action::Income, Action::Income], vec![], &hand, 2),
+            seat(vec![Action::Income, Action::Income], vec![], &hand, 2),
+            seat(vec![Action::Coup(0), Action::Income], vec![], &hand, 7),
+        ]);
+        game.set_rules(Ruleset::classic().with_stalemate_rounds(1));
+        game.run();
+        //the Coup on turn 2 restarts the count: one full round of three players after it
+        assert_eq!((game.quiet_since, game.turn.number), (3, 6));
+        assert_eq!(game.players.iter().map(|p| p.coins()).collect::<Vec<_>>(), vec![4, 4, 1]);
+        let result = game.result().unwrap();
+        assert_eq!((result.ending, result.winner), (Ending::Stalemate, Some(1)));
+        assert_eq!(result.placements, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn players_who_never_attack_are_stopped_by_the_batch_window() {
+        let hand = [Character::Captain, Character::Contessa];
+        let mut game = table(vec![
+            seat(vec![Action::Income; STALEMATE_ROUNDS], vec![], &hand, 2),
+            seat(vec![Action::Income; STALEMATE_ROUNDS], vec![], &hand, 1),
+        ]);
+        game.set_rules(Ruleset::classic().with_stalemate_rounds(STALEMATE_ROUNDS));
+        game.run();
+        let result = game.result().unwrap();
+        assert_eq!((result.ending, result.turns), (Ending::Stalemate, 2 * STALEMATE_ROUNDS));
+        //same influence, so the extra starting coin decides it
+        assert_eq!((game.players[0].coins(), game.players[1].coins()), (2 + STALEMATE_ROUNDS, 1 + STALEMATE_ROUNDS));
+        assert_eq!((result.winner, result.placements), (Some(0), vec![0, 1]));
+    }
+
+    #[test]
+    fn setup_applies_the_two_player_rule_and_the_draft() {
+        let mut game = table(vec![seat(vec![], vec![], &[], 2), seat(vec![], vec![], &[], 2)]);
+        let setup = Setup::default().with_seating(SeatOrder::Fixed).with_first_player(FirstPlayer::Seat(1)).with_two_player_rule().with_draft();
+        start_game_with(&mut game, &setup).unwrap();
+        assert_eq!(game.current_player, 1);
+        assert_eq!((game.players[0].coins(), game.players[1].coins()), (2, 1));
+        assert_eq!(game.events, vec![
+            Event::Seated { order: vec![0, 1] },
+            Event::FirstPlayer { player: 1 },
+            Event::Dealt { player: 0, cards: 2, coins: 2 },
+            Event::Dealt { player: 1, cards: 2, coins: 1 },
+            Event::Drafted { player: 1 },
+            Event::Drafted { player: 0 },
+        ]);
+        //every card is still either in a hand or in the deck
+        let mut cards = game.deck.clone();
+        cards.extend(game.players.iter().flat_map(|p| p.hand()));
+        let deck = Ruleset::classic().deck();
+        for character in Character::all() {
+            assert_eq!(cards.iter().filter(|&&c| c == character).count(), deck.iter().filter(|&&c| c == character).count());
+        }
+        assert_eq!(game.deck.len(), 11);
+    }
+
+    #[test]
+    fn recorded_setup_is_replayed() {
+        let players = || (0..3).map(|i| Box::new(ScriptedPlayer::new(&format!("p{}", i), vec![], vec![])) as Box<dyn Player>).collect();
+        let mut game = table(players());
+        let setup = Setup::default().with_first_player(FirstPlayer::Random).with_draft();
+        start_game_with(&mut game, &setup).unwrap();
+
+        let mut replay = table(players());
+        replay.rng = StdRng::seed_from_u64(1);
+        replay_setup(&mut replay, &game.events).unwrap();
+        assert_eq!(replay.events, game.events);
+        assert_eq!(replay.current_player, game.current_player);
+        for (original, replayed) in game.players.iter().zip(replay.players.iter()) {
+            assert_eq!((replayed.name(), replayed.coins(), replayed.hand().len()), (original.name(), original.coins(), 2));
+        }
+
+        let mut short = table(vec![seat(vec![], vec![], &[], 2), seat(vec![], vec![], &[], 2)]);
+        assert!(replay_setup(&mut short, &game.events).is_err());
+    }
+}
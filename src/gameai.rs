@@ -1,26 +1,163 @@
-use rusty_machine::prelude::Matrix;
-
-use crate::action::{Action, BlockAction, ChallengeAction};
+use crate::action::{Action, BlockAction};
 use crate::character::Character;
-use crate::game::GameState;
+use crate::event::Event;
+use crate::faction::Faction;
+use crate::game::{GameState, Turn};
 use crate::phase::Phase;
+use crate::response::TieBreak;
+use crate::result::Elimination;
+use crate::ruleset::Ruleset;
+use crate::team::Teams;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GameStateAI {
+    pub player_index: usize,
+    pub current_player: usize,
+    /// Player names by seat, e.g. to look opponents up in an `OpponentModel`.
+    pub names: Vec<String>,
+    pub phase: Phase,
     pub players_lives: Vec<usize>,
     pub players_coins: Vec<usize>,
     pub revealed_characters: Vec<Option<Character>>,
     pub cards_in_hand: Vec<Character>,
+    /// The action or block being responded to, and the player who made it.
+    pub pending_claim: Option<(Action, usize)>,
+    pub turn: Turn,
+    /// Everything public that happened so far, e.g. to feed a `BeliefState`.
+    pub events: Vec<Event>,
+    /// Faction by seat in a Reformation game.
+    pub factions: Option<Vec<Faction>>,
+    pub treasury: usize,
+    /// Characters in play, their powers and the coin amounts of the actions.
+    pub rules: Ruleset,
+    /// The opponent and card this player is examining, while deciding whether to force
+    /// an exchange.
+    pub examined: Option<(usize, Character)>,
+    /// Team by seat in a team game.
+    pub teams: Option<Teams>,
+    /// How simultaneous challenges and blocks are settled.
+    pub tie_break: TieBreak,
+    /// Players knocked out so far, in order.
+    pub eliminations: Vec<Elimination>,
+    /// First turn since anyone last lost influence.
+    pub quiet_since: usize,
 }
 
 
 impl GameStateAI {
-    pub fn from_gamestate(gamestate: &GameState, player_name: String) -> GameStateAI {
+    /// What the player in seat `player_index` can see of `gamestate`.
+    pub fn from_gamestate(gamestate: &GameState, player_index: usize) -> GameStateAI {
+        let pending_claim = match gamestate.phase {
+            Phase::Action => None,
+            _ => gamestate.history.iter().rev().find(|(action, _)| action.is_action_or_block()).cloned(),
+        };
+
         GameStateAI {
+            player_index,
+            current_player: gamestate.current_player,
+            names: gamestate.players.iter().map(|player| player.name()).collect(),
+            phase: gamestate.phase,
             players_lives: gamestate.players.iter().map(|player| player.hand().len()).collect(),
             players_coins: gamestate.players.iter().map(|player| player.coins()).collect(),
             revealed_characters: gamestate.revealed_characters.clone(),
-            cards_in_hand: gamestate.players.iter().find(|player| player.name() == player_name).unwrap().hand().clone(),
+            cards_in_hand: gamestate.players[player_index].hand(),
+            pending_claim,
+            turn: gamestate.turn.clone(),
+            events: gamestate.events.clone(),
+            factions: gamestate.factions.clone(),
+            treasury: gamestate.treasury,
+            rules: gamestate.rules.clone(),
+            examined: if player_index == gamestate.current_player { gamestate.examined } else { None },
+            teams: gamestate.teams.clone(),
+            tie_break: gamestate.tie_break,
+            eliminations: gamestate.eliminations.clone(),
+            quiet_since: gamestate.quiet_since,
+        }
+    }
+
+    /// Whether `player` is another member of this player's team.
+    pub fn is_teammate(&self, player: usize) -> bool {
+        player != self.player_index && self.teams.as_ref().is_some_and(|t| t.same_team(player, self.player_index))
+    }
+
+    /// Seat `player` as seen from this player: 0 is this player, 1 the next seat clockwise, ...
+    pub fn relative_seat(&self, player: usize) -> usize {
+        let n = self.players_lives.len();
+        (player + n - self.player_index) % n
+    }
+
+    /// Number of values produced by [`GameStateAI::features`] for a table of `num_players`.
+    pub fn feature_len(num_players: usize) -> usize {
+        2 * num_players + 3 * CHARACTERS + PHASES + CLAIMS + num_players + 1
+    }
+
+    /// Flat numeric encoding of the observation, with seats relative to this player so
+    /// that a model trained in one seat applies to the others.
+    ///
+    /// Layout: coins and lives per relative seat, own cards per character, revealed cards
+    /// per character, phase one-hot, pending claim one-hot, the characters that would
+    /// back the pending claim under the rules in play, claimant relative seat one-hot,
+    /// and whether the pending claim targets this player.
+    pub fn features(&self) -> Vec<f64> {
+        let n = self.players_lives.len();
+        let mut features = Vec::with_capacity(Self::feature_len(n));
+
+        for offset in 0..n {
+            let seat = (self.player_index + offset) % n;
+            features.push(self.players_coins[seat] as f64);
+            features.push(self.players_lives[seat] as f64);
+        }
+        for character in Character::all() {
+            features.push(self.cards_in_hand.iter().filter(|c| c.slot() == character.slot()).count() as f64);
+        }
+        for character in Character::all() {
+            features.push(self.revealed_characters.iter().flatten().filter(|c| c.slot() == character.slot()).count() as f64);
+        }
+
+        let mut phase = [0.0; PHASES];
+        phase[match self.phase {
+            Phase::Action => 0,
+            Phase::Block => 1,
+            Phase::Challenge => 2,
+        }] = 1.0;
+        features.extend_from_slice(&phase);
+
+        let mut claim = [0.0; CLAIMS];
+        let mut claimed = [0.0; CHARACTERS];
+        let mut claimant = vec![0.0; n];
+        let mut targets_me = 0.0;
+        if let Some((action, player)) = &self.pending_claim {
+            let kind = match action {
+                Action::Tax => Some(0),
+                Action::Assassinate(_) => Some(1),
+                Action::Steal(_, _) => Some(2),
+                Action::Exchange => Some(3),
+                Action::ForeignAid => Some(4),
+                Action::Block(BlockAction::Assassinate(_), _, _) => Some(5),
+                Action::Block(BlockAction::Stealing(_, _), _, _) => Some(6),
+                Action::Block(BlockAction::ForeignAid(_), _, _) => Some(7),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                claim[kind] = 1.0;
+            }
+            for character in self.rules.claimed(action) {
+                claimed[character.slot()] = 1.0;
+            }
+            claimant[self.relative_seat(*player)] = 1.0;
+            if action.target() == Some(self.player_index) {
+                targets_me = 1.0;
+            }
         }
+        features.extend_from_slice(&claim);
+        features.extend_from_slice(&claimed);
+        features.extend(claimant);
+        features.push(targets_me);
+
+        features
     }
-}
\ No newline at end of file
+}
+
+const CHARACTERS: usize = 5;
+const PHASES: usize = 3;
+const CLAIMS: usize = 8;
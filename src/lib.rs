@@ -0,0 +1,27 @@
+pub mod action;
+pub mod action_space;
+pub mod belief;
+pub mod cfr;
+pub mod character;
+pub mod config;
+pub mod dataset;
+pub mod env;
+pub mod event;
+pub mod faction;
+pub mod game;
+pub mod gameai;
+pub mod opponent;
+pub mod pack;
+pub mod phase;
+pub mod player;
+pub mod reinforce;
+mod probe;
+pub mod response;
+pub mod result;
+pub mod ruleset;
+pub mod selfplay;
+pub mod series;
+pub mod setup;
+pub mod simulation;
+pub mod team;
+pub mod tournament;
@@ -1,22 +1,56 @@
-use player::Player;
-use game::GameState;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
-use crate::player::{HumanPlayer, ai::AIPlayer};
-
-mod game;
-mod gameai;
-mod player;
-mod character;
-mod action;
-mod phase;
+use coup::action_space::ActionSpace;
+use coup::cfr::{self, CfrConfig, CfrSolver};
+use coup::config::STALEMATE_ROUNDS;
+use coup::game::{self, GameState};
+use coup::gameai::GameStateAI;
+use coup::dataset::Dataset;
+use coup::opponent::OpponentModel;
+use coup::pack::CharacterPack;
+use coup::reinforce::{ReinforceConfig, Trainer};
+use coup::ruleset::Ruleset;
+use coup::player::{HumanPlayer, Player};
+use coup::player::neural::{PolicyNet, TrainingConfig};
+use coup::player::spec::AgentSpec;
+use coup::selfplay::{self, SelfPlayConfig};
+use coup::series::{self, Match, MatchConfig};
+use coup::setup::{FirstPlayer, SeatOrder, Setup};
+use coup::team::Teams;
+use coup::simulation::{self, SimulationConfig};
+use coup::tournament::{self, Pairing, TournamentConfig};
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("selfplay") => run_selfplay(&args[1..]),
+        Some("cfr") => run_cfr(&args[1..]),
+        Some("tournament") => run_tournament(&args[1..]),
+        Some("match") => run_match(&args[1..]),
+        Some("bench") => run_bench(&args[1..]),
+        Some("imitate") => run_imitate(&args[1..]),
+        Some("reinforce") => run_reinforce(&args[1..]),
+        _ => play_interactive(&args),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `coup [--reformation] [--inquisitor] [--rules FILE] [--deck ROLES] [house rules] [--teams N [--friendly-fire] [--give]] [setup]`
+///
+/// House rules are the options read by [`ruleset`], setup those read by [`setup`]. With
+/// `--teams`, players are split into N teams in the order their names are entered.
+fn play_interactive(args: &[String]) -> Result<(), String> {
+    let rules = ruleset(args)?;
     //ask how many players
     let mut num_players = String::new();
     std::io::stdin().read_line(&mut num_players).expect("Failed to read line");
     let num_players: usize = num_players.trim_end().parse().expect("Please type a number!");
     //ask for names
-    let mut players: Vec<Box<(dyn Player + 'static)>> = Vec::new();
+    let mut players: Vec<Box<dyn Player>> = Vec::new();
     for _ in 0..num_players {
         let mut name = String::new();
         std::io::stdin().read_line(&mut name).expect("Failed to read line");
@@ -26,9 +60,407 @@ fn main() {
     //create an ai player
     //players.push(Box::new(AIPlayer::new("AI".to_string())));
 
+    rules.check_players(players.len())?;
+    let teams = teams(args, players.len())?;
     let mut game = GameState::new(players);
-    game::start_game(&mut game);
+    if let Some(teams) = teams {
+        game = game.with_teams(teams);
+    }
+    game.set_rules(rules);
+    game::start_game_with(&mut game, &setup(args)?)?;
+    if args.iter().any(|a| a == "--reformation") {
+        game.enable_reformation();
+    }
     println!("{}", game);
-    
+
     game.run();
+    Ok(())
+}
+
+/// `coup selfplay [--games N] [--agents a,b,c] [--threads T] [--seed S] [--out FILE] [--format csv|bin] [--opponent-model FILE]
+/// [--inquisitor] [--rules FILE] [--deck ROLES] [house rules]`
+///
+/// With `--opponent-model`, agents get the statistics stored in FILE (if it exists) and
+/// the file is updated with the new games afterwards.
+fn run_selfplay(args: &[String]) -> Result<(), String> {
+    let mut config = SelfPlayConfig::default();
+    if let Some(games) = option(args, "--games") {
+        config.games = games.parse().map_err(|_| format!("Invalid --games '{}'", games))?;
+    }
+    if let Some(agents) = option(args, "--agents") {
+        config.agents = AgentSpec::parse_list(agents)?;
+    }
+    if let Some(threads) = option(args, "--threads") {
+        config.threads = threads.parse().map_err(|_| format!("Invalid --threads '{}'", threads))?;
+    }
+    if let Some(seed) = option(args, "--seed") {
+        config.seed = seed.parse().map_err(|_| format!("Invalid --seed '{}'", seed))?;
+    }
+    config.rules = batch_ruleset(args)?;
+    let out = option(args, "--out").unwrap_or("selfplay.csv");
+    let format = option(args, "--format").unwrap_or(if out.ends_with(".bin") { "bin" } else { "csv" });
+
+    let model_path = option(args, "--opponent-model");
+    let mut model = match model_path {
+        Some(path) if std::path::Path::new(path).exists() => OpponentModel::load(path)?,
+        _ => OpponentModel::new(),
+    };
+    if model_path.is_some() {
+        config.opponent_model = Some(std::sync::Arc::new(model.clone()));
+    }
+
+    let (dataset, stats) = selfplay::generate(&config)?;
+    if let Some(path) = model_path {
+        model.merge(&stats);
+        model.save(path)?;
+    }
+    match format {
+        "csv" => dataset.save_csv(out)?,
+        "bin" => dataset.save_binary(out)?,
+        _ => return Err(format!("Unknown --format '{}'", format)),
+    }
+    println!("Wrote {} decisions from {} games to {}", dataset.len(), config.games, out);
+    Ok(())
+}
+
+/// `coup cfr [--iterations N] [--players P] [--turns T] [--seed S] [--samples N] [--out FILE]`
+///
+/// Trains an average strategy with external-sampling MCCFR on games cut off after `T`
+/// turns and, for 2 players, estimates its exploitability from `--samples` games.
+fn run_cfr(args: &[String]) -> Result<(), String> {
+    let mut config = CfrConfig::default();
+    let mut iterations = 1000;
+    let mut samples = 500;
+    if let Some(n) = option(args, "--iterations") {
+        iterations = n.parse().map_err(|_| format!("Invalid --iterations '{}'", n))?;
+    }
+    if let Some(players) = option(args, "--players") {
+        config.players = players.parse().map_err(|_| format!("Invalid --players '{}'", players))?;
+    }
+    if let Some(turns) = option(args, "--turns") {
+        config.max_turns = turns.parse().map_err(|_| format!("Invalid --turns '{}'", turns))?;
+    }
+    if let Some(seed) = option(args, "--seed") {
+        config.seed = seed.parse().map_err(|_| format!("Invalid --seed '{}'", seed))?;
+    }
+    if let Some(n) = option(args, "--samples") {
+        samples = n.parse().map_err(|_| format!("Invalid --samples '{}'", n))?;
+    }
+    if !(2..=6).contains(&config.players) {
+        return Err("--players must be between 2 and 6".to_string());
+    }
+    let out = option(args, "--out").unwrap_or("strategy.cfr");
+
+    let mut solver = CfrSolver::new(config);
+    solver.run(iterations);
+    let strategy = solver.average_strategy();
+    strategy.save(out)?;
+    println!("Trained {} iterations over {} information sets, wrote {}", iterations, strategy.len(), out);
+    if config.players == 2 && samples > 0 {
+        println!("Estimated exploitability: {:.4}", cfr::exploitability(&strategy, &config, samples)?);
+    }
+    Ok(())
+}
+
+/// `coup tournament --agents a,b,c,... [--table-size K] [--format round-robin|swiss] [--rounds R]
+/// [--games N] [--best-of N] [--seed S] [--threads T] [--inquisitor] [--rules FILE] [--deck ROLES] [house rules]`
+///
+/// `--games` is the number of games each table plays per rotation of its seating. With
+/// `--best-of`, each of those games is a match won by the player with most wins.
+fn run_tournament(args: &[String]) -> Result<(), String> {
+    let mut config = TournamentConfig::default();
+    if let Some(agents) = option(args, "--agents") {
+        config.agents = AgentSpec::parse_list(agents)?;
+    }
+    if let Some(size) = option(args, "--table-size") {
+        config.table_size = size.parse().map_err(|_| format!("Invalid --table-size '{}'", size))?;
+    }
+    let rounds = match option(args, "--rounds") {
+        Some(rounds) => rounds.parse().map_err(|_| format!("Invalid --rounds '{}'", rounds))?,
+        None => 5,
+    };
+    config.pairing = match option(args, "--format").unwrap_or("round-robin") {
+        "round-robin" => Pairing::RoundRobin,
+        "swiss" => Pairing::Swiss { rounds },
+        format => return Err(format!("Unknown --format '{}'", format)),
+    };
+    if let Some(games) = option(args, "--games") {
+        config.games_per_seating = games.parse().map_err(|_| format!("Invalid --games '{}'", games))?;
+    }
+    parse(args, "--best-of", &mut config.best_of)?;
+    config.rules = batch_ruleset(args)?;
+    if let Some(seed) = option(args, "--seed") {
+        config.seed = seed.parse().map_err(|_| format!("Invalid --seed '{}'", seed))?;
+    }
+    if let Some(threads) = option(args, "--threads") {
+        config.threads = threads.parse().map_err(|_| format!("Invalid --threads '{}'", threads))?;
+    }
+
+    let standings = tournament::run(&config)?;
+    print!("{}", tournament::report(&standings));
+    Ok(())
+}
+
+/// `coup match --agents a,b,c [--games N] [--seed S] [--play-out] [--win-points P] [--place-points P]
+/// [--reformation] [--inquisitor] [--rules FILE] [--deck ROLES] [house rules] [setup]`
+///
+/// Plays a best-of-N series with the first seat rotating every game, printing each
+/// game's finishing order and the final standings. Seats stay fixed unless
+/// `--random-seats` is given.
+fn run_match(args: &[String]) -> Result<(), String> {
+    let agents = AgentSpec::parse_list(option(args, "--agents").ok_or("--agents is required")?)?;
+    let mut config = MatchConfig { rules: batch_ruleset(args)?, play_out: args.iter().any(|a| a == "--play-out"), ..MatchConfig::default() };
+    parse(args, "--games", &mut config.games)?;
+    parse(args, "--seed", &mut config.seed)?;
+    parse(args, "--win-points", &mut config.scoring.win)?;
+    parse(args, "--place-points", &mut config.scoring.outlasted)?;
+    config.reformation = args.iter().any(|a| a == "--reformation");
+    config.setup = setup(args)?;
+    if !args.iter().any(|a| a == "--random-seats") {
+        config.setup = config.setup.with_seating(SeatOrder::Fixed);
+    }
+
+    let mut series = Match::new(agents, config)?;
+    while !series.is_over() {
+        let order = series.play_game()?;
+        let names: Vec<&str> = order.iter().map(|&i| series.standings[i].name.as_str()).collect();
+        println!("Game {}: {}", series.results.len(), names.join(" > "));
+    }
+    print!("{}", series::report(&series.table()));
+    Ok(())
+}
+
+/// `coup bench [--games N] [--agents a,b,c] [--threads T] [--seed S] [--reformation] [--inquisitor] [--rules FILE] [--deck ROLES]
+/// [house rules] [--teams N [--friendly-fire] [--give]] [setup]`
+///
+/// Plays headless games and reports engine throughput.
+fn run_bench(args: &[String]) -> Result<(), String> {
+    let mut config = SimulationConfig::default();
+    if let Some(games) = option(args, "--games") {
+        config.games = games.parse().map_err(|_| format!("Invalid --games '{}'", games))?;
+    }
+    if let Some(agents) = option(args, "--agents") {
+        config.agents = AgentSpec::parse_list(agents)?;
+    }
+    if let Some(threads) = option(args, "--threads") {
+        config.threads = threads.parse().map_err(|_| format!("Invalid --threads '{}'", threads))?;
+    }
+    if let Some(seed) = option(args, "--seed") {
+        config.seed = seed.parse().map_err(|_| format!("Invalid --seed '{}'", seed))?;
+    }
+    config.reformation = args.iter().any(|a| a == "--reformation");
+    config.rules = batch_ruleset(args)?;
+    config.teams = teams(args, config.agents.len())?;
+    config.setup = setup(args)?;
+
+    let bench = simulation::benchmark(&config)?;
+    println!(
+        "{} games, {} turns in {:.3}s on {} threads: {:.0} games/s, {:.0} turns/s",
+        bench.games, bench.turns, bench.elapsed.as_secs_f64(), config.threads.max(1), bench.games_per_second(), bench.turns_per_second(),
+    );
+    Ok(())
+}
+
+/// `coup imitate --data FILE[,FILE] [--hidden 64[,32]] [--epochs N] [--rate R] [--momentum M] [--seed S] [--winners-only] [--out FILE]`
+///
+/// Trains a policy network to copy the decisions in self-play logs, e.g. from
+/// `coup selfplay --agents ismcts,ismcts,ismcts`. The player count follows from the
+/// data; the network can then play as `nn:<out>`.
+fn run_imitate(args: &[String]) -> Result<(), String> {
+    let data = option(args, "--data").ok_or("--data is required")?;
+    let mut dataset = Dataset::new();
+    for path in data.split(',') {
+        dataset.extend(if path.ends_with(".bin") { Dataset::load_binary(path)? } else { Dataset::load_csv(path)? });
+    }
+    if args.iter().any(|a| a == "--winners-only") {
+        dataset.samples.retain(|s| s.outcome > 0.0);
+    }
+    let hidden = match option(args, "--hidden") {
+        Some(hidden) => hidden.split(',').map(|h| h.parse::<usize>().map_err(|_| format!("Invalid --hidden '{}'", hidden))).collect::<Result<Vec<_>, _>>()?,
+        None => vec![64],
+    };
+    let mut config = TrainingConfig::default();
+    if let Some(epochs) = option(args, "--epochs") {
+        config.epochs = epochs.parse().map_err(|_| format!("Invalid --epochs '{}'", epochs))?;
+    }
+    if let Some(rate) = option(args, "--rate") {
+        config.learning_rate = rate.parse().map_err(|_| format!("Invalid --rate '{}'", rate))?;
+    }
+    if let Some(momentum) = option(args, "--momentum") {
+        config.momentum = momentum.parse().map_err(|_| format!("Invalid --momentum '{}'", momentum))?;
+    }
+    let seed = match option(args, "--seed") {
+        Some(seed) => seed.parse().map_err(|_| format!("Invalid --seed '{}'", seed))?,
+        None => 0,
+    };
+    let out = option(args, "--out").unwrap_or("policy.nn");
+
+    let num_features = dataset.feature_len()?;
+    let num_players = (2..=6).find(|&n| GameStateAI::feature_len(n) == num_features).ok_or("The data does not come from a 2 to 6 player game")?;
+    let mut policy = PolicyNet::new(num_players, &hidden, seed);
+    policy.train(&dataset, &config)?;
+    policy.save(out)?;
+
+    let space = ActionSpace::new(num_players);
+    let agreed = dataset.samples.iter().filter(|s| {
+        let p = policy.probabilities(&s.features, &s.legal).unwrap_or_default();
+        let best = (0..space.len()).filter(|&i| s.legal.get(i) == Some(&true)).max_by(|&a, &b| p[a].total_cmp(&p[b]));
+        best == Some(s.label)
+    }).count();
+    println!("Trained a {}-player network on {} decisions ({:.1}% matched) and wrote it to {}", num_players, dataset.len(), 100.0 * agreed as f64 / dataset.len() as f64, out);
+    Ok(())
+}
+
+/// `coup reinforce [--players P] [--init FILE] [--hidden 64[,32]] [--iterations N] [--games G] [--rate R] [--epochs E] [--clip C] [--heuristic-share S] [--checkpoint-every K] [--pool N] [--baselines a,b] [--eval-every K] [--eval-games N] [--seed S] [--curve FILE] [--checkpoints DIR] [--out FILE]`
+///
+/// Improves a policy network by self-play against its past checkpoints and the
+/// heuristic bot. `--epochs` above 1 switches from REINFORCE to PPO-style clipped updates.
+fn run_reinforce(args: &[String]) -> Result<(), String> {
+    let mut config = ReinforceConfig {
+        curve: Some("learning_curve.csv".into()),
+        ..ReinforceConfig::default()
+    };
+    parse(args, "--players", &mut config.num_players)?;
+    parse(args, "--iterations", &mut config.iterations)?;
+    parse(args, "--games", &mut config.games_per_iteration)?;
+    parse(args, "--rate", &mut config.learning_rate)?;
+    parse(args, "--epochs", &mut config.epochs)?;
+    parse(args, "--clip", &mut config.clip)?;
+    parse(args, "--heuristic-share", &mut config.heuristic_share)?;
+    parse(args, "--checkpoint-every", &mut config.checkpoint_every)?;
+    parse(args, "--pool", &mut config.pool_size)?;
+    parse(args, "--eval-every", &mut config.eval_every)?;
+    parse(args, "--eval-games", &mut config.eval_games)?;
+    parse(args, "--seed", &mut config.seed)?;
+    if let Some(hidden) = option(args, "--hidden") {
+        config.hidden = hidden.split(',').map(|h| h.parse::<usize>().map_err(|_| format!("Invalid --hidden '{}'", hidden))).collect::<Result<Vec<_>, _>>()?;
+    }
+    if let Some(baselines) = option(args, "--baselines") {
+        config.baselines = AgentSpec::parse_list(baselines)?;
+    }
+    if let Some(curve) = option(args, "--curve") {
+        config.curve = Some(curve.into());
+    }
+    config.checkpoint_dir = option(args, "--checkpoints").map(|dir| dir.into());
+    let init = match option(args, "--init") {
+        Some(path) => Some(PolicyNet::load(path)?),
+        None => None,
+    };
+    if let Some(init) = &init {
+        config.num_players = init.num_players;
+    }
+    let out = option(args, "--out").unwrap_or("policy.nn");
+
+    let mut trainer = Trainer::new(config, init)?;
+    let history = trainer.run()?;
+    trainer.policy.save(out)?;
+    if let Some(evaluation) = history.iter().rev().find_map(|s| s.evaluation.as_ref()) {
+        for (baseline, rate) in trainer.config.baselines.iter().zip(evaluation) {
+            println!("vs {}: {:.1}% wins", baseline, 100.0 * rate);
+        }
+    }
+    println!("Wrote the policy to {}", out);
+    Ok(())
+}
+
+/// The ruleset picked by `--rules FILE` (TOML, or JSON for `.json`) or `--inquisitor`,
+/// the base game otherwise. `--deck Socialist,Reporter` swaps in roles from the
+/// character packs, `--deck random` draws one for every slot from `--seed`. Any house rules given as
+/// `--starting-coins N`, `--influence N`, `--coup-cost N`, `--forced-coup N`,
+/// `--assassinate-cost N`, `--steal N`, `--foreign-aid N`, `--tax N`, `--max-turns N` or
+/// `--stalemate-rounds N` go on top.
+fn ruleset(args: &[String]) -> Result<Ruleset, String> {
+    let mut rules = match option(args, "--rules") {
+        Some(path) => Ruleset::load(path)?,
+        None if args.iter().any(|a| a == "--inquisitor") => Ruleset::inquisitor(),
+        None => Ruleset::classic(),
+    };
+    match option(args, "--deck") {
+        Some("random") => {
+            let mut seed = 0;
+            parse(args, "--seed", &mut seed)?;
+            rules = rules.with_random_roles(&mut StdRng::seed_from_u64(seed));
+        },
+        Some(deck) => {
+            let roles = deck.split(',').map(|name| CharacterPack::by_name(name).ok_or(format!("Unknown character '{}'", name))).collect::<Result<Vec<_>, _>>()?;
+            rules = rules.with_roles(&roles)?;
+        },
+        None => {},
+    }
+    let config = &mut rules.config;
+    parse(args, "--starting-coins", &mut config.starting_coins)?;
+    parse(args, "--influence", &mut config.influence)?;
+    parse(args, "--coup-cost", &mut config.coup_cost)?;
+    parse(args, "--forced-coup", &mut config.forced_coup)?;
+    parse(args, "--assassinate-cost", &mut config.assassinate_cost)?;
+    parse(args, "--steal", &mut config.steal)?;
+    parse(args, "--foreign-aid", &mut config.foreign_aid)?;
+    parse(args, "--tax", &mut config.tax)?;
+    if let Some(turns) = option(args, "--max-turns") {
+        config.max_turns = Some(turns.parse().map_err(|_| format!("Invalid --max-turns '{}'", turns))?);
+    }
+    if let Some(rounds) = option(args, "--stalemate-rounds") {
+        config.stalemate_rounds = Some(rounds.parse().map_err(|_| format!("Invalid --stalemate-rounds '{}'", rounds))?);
+    }
+    rules.validate()?;
+    Ok(rules)
+}
+
+/// [`ruleset`] for the commands that play games unattended: unless the rules already
+/// limit the game, it stops after [`STALEMATE_ROUNDS`] rounds without a lost influence.
+fn batch_ruleset(args: &[String]) -> Result<Ruleset, String> {
+    let mut rules = ruleset(args)?;
+    if rules.config.max_turns.is_none() && rules.config.stalemate_rounds.is_none() {
+        rules.config.stalemate_rounds = Some(STALEMATE_ROUNDS);
+    }
+    Ok(rules)
+}
+
+/// Teams for `num_players` from `--teams N`, seated alternately, with
+/// `--friendly-fire` and `--give` switching on attacks and gifts between teammates.
+fn teams(args: &[String], num_players: usize) -> Result<Option<Teams>, String> {
+    let mut num_teams = 0;
+    parse(args, "--teams", &mut num_teams)?;
+    if num_teams == 0 {
+        return Ok(None);
+    }
+    let mut teams = Teams::alternating(num_players, num_teams);
+    teams.friendly_fire = args.iter().any(|a| a == "--friendly-fire");
+    teams.giving = args.iter().any(|a| a == "--give");
+    teams.validate(num_players)?;
+    Ok(Some(teams))
+}
+
+/// Setup options: `--fixed-seats` keeps the players in the order given, `--first-player
+/// SEAT|random` picks who starts, `--two-player-rule` starts them on 1 coin in two-player
+/// games and `--draft` deals one extra card to return.
+fn setup(args: &[String]) -> Result<Setup, String> {
+    let mut setup = Setup::default();
+    if args.iter().any(|a| a == "--fixed-seats") {
+        setup = setup.with_seating(SeatOrder::Fixed);
+    }
+    match option(args, "--first-player") {
+        Some("random") => setup = setup.with_first_player(FirstPlayer::Random),
+        Some(seat) => setup = setup.with_first_player(FirstPlayer::Seat(seat.parse().map_err(|_| format!("Invalid --first-player '{}'", seat))?)),
+        None => {},
+    }
+    if args.iter().any(|a| a == "--two-player-rule") {
+        setup = setup.with_two_player_rule();
+    }
+    if args.iter().any(|a| a == "--draft") {
+        setup = setup.with_draft();
+    }
+    Ok(setup)
+}
+
+fn parse<T: std::str::FromStr>(args: &[String], name: &str, value: &mut T) -> Result<(), String> {
+    if let Some(v) = option(args, name) {
+        *value = v.parse().map_err(|_| format!("Invalid {} '{}'", name, v))?;
+    }
+    Ok(())
+}
+
+/// Value following `name` on the command line, if present.
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).map(|s| s.as_str())
 }
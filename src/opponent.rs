@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::action::Action;
+use crate::belief::BluffModel;
+use crate::character::Character;
+use crate::event::Event;
+use crate::phase::Phase;
+use crate::ruleset::Ruleset;
+
+/// Weight, in observations, of the default `BluffModel` when estimating rates, so that a
+/// player seen a handful of times is not judged on those alone.
+const PRIOR_WEIGHT: f64 = 4.0;
+
+/// Habits of one player, accumulated over every game recorded for them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerStats {
+    pub games: usize,
+    pub wins: usize,
+    /// Claims made by acting or blocking, per `Character::slot`, so the Inquisitor
+    /// counts as the Ambassador. A Captain/Ambassador block counts for both.
+    pub claims: Vec<usize>,
+    pub claims_challenged: usize,
+    /// Challenged claims that turned out to be bluffs.
+    pub bluffs_caught: usize,
+    /// Challenge windows the player answered, and how often they challenged.
+    pub challenge_chances: usize,
+    pub challenges: usize,
+    pub challenges_won: usize,
+    /// Block windows the player answered, and how often they blocked.
+    pub block_chances: usize,
+    pub blocks: usize,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        PlayerStats {
+            games: 0,
+            wins: 0,
+            claims: vec![0; Character::all().len()],
+            claims_challenged: 0,
+            bluffs_caught: 0,
+            challenge_chances: 0,
+            challenges: 0,
+            challenges_won: 0,
+            block_chances: 0,
+            blocks: 0,
+        }
+    }
+}
+
+impl PlayerStats {
+    /// Share of this player's claims naming `character`.
+    pub fn claim_frequency(&self, character: Character) -> f64 {
+        let total: usize = self.claims.iter().sum();
+        let index = character.slot();
+        if total == 0 { 0.0 } else { self.claims[index] as f64 / total as f64 }
+    }
+
+    /// Share of challenged claims that were bluffs.
+    pub fn bluff_rate(&self) -> f64 {
+        smoothed(self.bluffs_caught, self.claims_challenged, BluffModel::default().bluff_rate)
+    }
+
+    pub fn challenge_rate(&self) -> f64 {
+        smoothed(self.challenges, self.challenge_chances, BluffModel::default().challenge_rate)
+    }
+
+    pub fn block_rate(&self) -> f64 {
+        smoothed(self.blocks, self.block_chances, 0.5)
+    }
+
+    /// The player's habits in the form `BeliefState` uses.
+    pub fn bluff_model(&self) -> BluffModel {
+        BluffModel {
+            bluff_rate: self.bluff_rate(),
+            challenge_rate: self.challenge_rate(),
+            ..BluffModel::default()
+        }
+    }
+
+    fn merge(&mut self, other: &PlayerStats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        for (a, b) in self.claims.iter_mut().zip(other.claims.iter()) {
+            *a += b;
+        }
+        self.claims_challenged += other.claims_challenged;
+        self.bluffs_caught += other.bluffs_caught;
+        self.challenge_chances += other.challenge_chances;
+        self.challenges += other.challenges;
+        self.challenges_won += other.challenges_won;
+        self.block_chances += other.block_chances;
+        self.blocks += other.blocks;
+    }
+
+    fn fields(&self) -> Vec<usize> {
+        let mut fields = vec![self.games, self.wins];
+        fields.extend(self.claims.iter());
+        fields.extend([self.claims_challenged, self.bluffs_caught, self.challenge_chances, self.challenges, self.challenges_won, self.block_chances, self.blocks]);
+        fields
+    }
+
+    fn field_names() -> Vec<String> {
+        let mut names = vec!["games".to_string(), "wins".to_string()];
+        names.extend(Character::all().iter().map(|c| format!("claims_{}", c.to_string().to_lowercase())));
+        names.extend(["claims_challenged", "bluffs_caught", "challenge_chances", "challenges", "challenges_won", "block_chances", "blocks"].iter().map(|s| s.to_string()));
+        names
+    }
+
+    fn from_fields(fields: &[usize]) -> PlayerStats {
+        let n = Character::all().len();
+        PlayerStats {
+            games: fields[0],
+            wins: fields[1],
+            claims: fields[2..2 + n].to_vec(),
+            claims_challenged: fields[2 + n],
+            bluffs_caught: fields[3 + n],
+            challenge_chances: fields[4 + n],
+            challenges: fields[5 + n],
+            challenges_won: fields[6 + n],
+            block_chances: fields[7 + n],
+            blocks: fields[8 + n],
+        }
+    }
+}
+
+fn smoothed(count: usize, total: usize, prior: f64) -> f64 {
+    (count as f64 + PRIOR_WEIGHT * prior) / (total as f64 + PRIOR_WEIGHT)
+}
+
+/// Per-name statistics about how players bluff, challenge and block, collected from
+/// game event logs and kept on disk between sessions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpponentModel {
+    pub players: HashMap<String, PlayerStats>,
+}
+
+impl OpponentModel {
+    pub fn new() -> OpponentModel {
+        OpponentModel { players: HashMap::new() }
+    }
+
+    pub fn stats(&self, name: &str) -> Option<&PlayerStats> {
+        self.players.get(name)
+    }
+
+    /// What to assume about `name`; the default model for players never seen.
+    pub fn bluff_model(&self, name: &str) -> BluffModel {
+        self.stats(name).map(|s| s.bluff_model()).unwrap_or_default()
+    }
+
+    /// Adds one finished game played by `rules`. `names` are the players by seat and
+    /// `events` the game's public log.
+    pub fn record_game(&mut self, names: &[String], events: &[Event], winner: Option<usize>, rules: &Ruleset) {
+        let mut game: Vec<PlayerStats> = vec![PlayerStats { games: 1, ..PlayerStats::default() }; names.len()];
+        if let Some(winner) = winner {
+            game[winner].wins += 1;
+        }
+        for event in events {
+            match event {
+                Event::Played { player, action } => {
+                    let mut slots: Vec<usize> = rules.claimed(action).iter().map(|c| c.slot()).collect();
+                    slots.sort_unstable();
+                    slots.dedup();
+                    for index in slots {
+                        game[*player].claims[index] += 1;
+                    }
+                    match action {
+                        Action::Challenge(_, _) => {
+                            game[*player].challenge_chances += 1;
+                            game[*player].challenges += 1;
+                        },
+                        Action::Block(_, _, _) => {
+                            game[*player].block_chances += 1;
+                            game[*player].blocks += 1;
+                        },
+                        _ => {},
+                    }
+                },
+                Event::Passed { player, phase: Phase::Challenge } => game[*player].challenge_chances += 1,
+                Event::Passed { player, phase: Phase::Block } => game[*player].block_chances += 1,
+                Event::Passed { .. } => {},
+                Event::ChallengeResolved { claimant, challenger, proven, .. } => {
+                    game[*claimant].claims_challenged += 1;
+                    if !proven {
+                        game[*claimant].bluffs_caught += 1;
+                        game[*challenger].challenges_won += 1;
+                    }
+                },
+                _ => {},
+            }
+        }
+        for (name, stats) in names.iter().zip(game.iter()) {
+            self.players.entry(name.clone()).or_default().merge(stats);
+        }
+    }
+
+    /// Adds everything recorded in `other`.
+    pub fn merge(&mut self, other: &OpponentModel) {
+        for (name, stats) in other.players.iter() {
+            self.players.entry(name.clone()).or_default().merge(stats);
+        }
+    }
+
+    /// Writes a tab-separated table with a header row and one player per line.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+        writeln!(out, "name\t{}", PlayerStats::field_names().join("\t")).map_err(|e| e.to_string())?;
+        let mut names: Vec<&String> = self.players.keys().collect();
+        names.sort();
+        for name in names {
+            let fields: Vec<String> = self.players[name].fields().iter().map(|f| f.to_string()).collect();
+            writeln!(out, "{}\t{}", name, fields.join("\t")).map_err(|e| e.to_string())?;
+        }
+        out.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OpponentModel, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(file).lines();
+        let header = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => return Ok(OpponentModel::new()),
+        };
+        let expected = format!("name\t{}", PlayerStats::field_names().join("\t"));
+        if header != expected {
+            return Err("Unexpected opponent model header".to_string());
+        }
+
+        let mut model = OpponentModel::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |e: String| format!("line {}: {}", i + 2, e);
+            let mut columns = line.split('\t');
+            let name = columns.next().unwrap_or_default().to_string();
+            let fields = columns.map(|f| f.parse::<usize>()).collect::<Result<Vec<usize>, _>>().map_err(|e| error(e.to_string()))?;
+            if fields.len() != PlayerStats::field_names().len() {
+                return Err(error(format!("expected {} fields, found {}", PlayerStats::field_names().len(), fields.len())));
+            }
+            model.players.insert(name, PlayerStats::from_fields(&fields));
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpponentModel;
+    use crate::action::{Action, ChallengeAction};
+    use crate::character::Character;
+    use crate::event::Event;
+    use crate::phase::Phase;
+    use crate::ruleset::Ruleset;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    /// Two games: alice is caught bluffing Tax by bob, then passes on a block against carol.
+    fn recorded() -> OpponentModel {
+        let mut model = OpponentModel::new();
+        model.record_game(&names(&["alice", "bob", "carol"]), &[
+            Event::Played { player: 0, action: Action::Tax },
+            Event::Played { player: 1, action: Action::Challenge(ChallengeAction::Tax, 0) },
+            Event::ChallengeResolved { claimant: 0, challenger: 1, claim: Action::Tax, proven: false },
+            Event::Passed { player: 2, phase: Phase::Challenge },
+        ], Some(1), &Ruleset::classic());
+        model.record_game(&names(&["carol", "alice"]), &[
+            Event::Played { player: 0, action: Action::ForeignAid },
+            Event::Passed { player: 1, phase: Phase::Block },
+        ], None, &Ruleset::classic());
+        model
+    }
+
+    #[test]
+    fn events_are_counted_by_player_name() {
+        let model = recorded();
+        let alice = model.stats("alice").unwrap();
+        assert_eq!((alice.games, alice.wins, alice.claims[Character::Duke.slot()]), (2, 0, 1));
+        assert_eq!((alice.claims_challenged, alice.bluffs_caught, alice.block_chances, alice.blocks), (1, 1, 1, 0));
+        let bob = model.stats("bob").unwrap();
+        assert_eq!((bob.games, bob.wins, bob.challenge_chances, bob.challenges, bob.challenges_won), (1, 1, 1, 1, 1));
+        let carol = model.stats("carol").unwrap();
+        assert_eq!((carol.games, carol.challenge_chances, carol.challenges), (2, 1, 0));
+    }
+
+    #[test]
+    fn saved_model_loads_back_unchanged() {
+        let model = recorded();
+        let path = std::env::temp_dir().join(format!("coup-opponents-{}.tsv", std::process::id()));
+        model.save(&path).unwrap();
+        let loaded = OpponentModel::load(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), model);
+    }
+}
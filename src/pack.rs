@@ -0,0 +1,61 @@
+use crate::character::Character;
+use crate::ruleset::{ActionKind, CharacterRules, Ruleset};
+
+/// A set of roles that can be mixed into a deck with [`Ruleset::with_roles`]. Every role
+/// declares the actions claiming it allows and the actions it blocks; either claim can
+/// be challenged, and is proven by holding the role.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterPack {
+    pub name: String,
+    pub roles: Vec<CharacterRules>,
+}
+
+impl CharacterPack {
+    /// The five roles of the base game.
+    pub fn base() -> CharacterPack {
+        CharacterPack { name: "Base".to_string(), roles: Ruleset::classic().characters }
+    }
+
+    pub fn inquisitor() -> CharacterPack {
+        let roles = Ruleset::inquisitor().characters.into_iter().filter(|r| r.character == Character::Inquisitor).collect();
+        CharacterPack { name: "Inquisitor".to_string(), roles }
+    }
+
+    /// Alternative roles in the style of Coup: Anarchy. The Socialist and the Speculator
+    /// stand in for the Duke, the Reporter for the Ambassador.
+    pub fn anarchy() -> CharacterPack {
+        let role = |character, actions: &[ActionKind], blocks: &[ActionKind]| CharacterRules {
+            character,
+            copies: 3,
+            actions: actions.to_vec(),
+            blocks: blocks.to_vec(),
+        };
+        CharacterPack {
+            name: "Anarchy".to_string(),
+            roles: vec![
+                role(Character::Socialist, &[ActionKind::Redistribute], &[ActionKind::ForeignAid]),
+                role(Character::Speculator, &[ActionKind::Speculate], &[]),
+                role(Character::Reporter, &[ActionKind::Report], &[ActionKind::Steal]),
+            ],
+        }
+    }
+
+    pub fn all() -> Vec<CharacterPack> {
+        vec![CharacterPack::base(), CharacterPack::inquisitor(), CharacterPack::anarchy()]
+    }
+
+    /// The rules of `character`, from whichever pack has it.
+    pub fn role(character: Character) -> Option<CharacterRules> {
+        CharacterPack::all().into_iter().flat_map(|p| p.roles).find(|r| r.character == character)
+    }
+
+    /// The role called `name`, ignoring case.
+    pub fn by_name(name: &str) -> Option<Character> {
+        CharacterPack::all().into_iter().flat_map(|p| p.roles).map(|r| r.character).find(|c| c.to_string().eq_ignore_ascii_case(name))
+    }
+
+    /// Every role of every pack that can fill `slot`.
+    pub fn candidates(slot: usize) -> Vec<CharacterRules> {
+        CharacterPack::all().into_iter().flat_map(|p| p.roles).filter(|r| r.character.slot() == slot).collect()
+    }
+}
@@ -1,200 +1,207 @@
-use rand::seq::SliceRandom;
-use rusty_machine::learning::naive_bayes::{NaiveBayes, Gaussian};
-use rusty_machine::linalg::Matrix;
-use rusty_machine::prelude::*;
-
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
 
 use crate::character::Character;
+use crate::dataset::Dataset;
 use crate::game::GameState;
 use crate::action::Action;
+use crate::action_space::ActionSpace;
 use crate::gameai::GameStateAI;
 
 use super::Player;
+use super::heuristic::least_valuable_card;
+
+const FILE_HEADER: &str = "coup-naive-bayes 2";
+
+/// Added to every per-class feature variance. One-hot features are often constant within
+/// a class, and a zero variance would rule the class out for any other value.
+const VARIANCE_EPSILON: f64 = 1e-2;
+
+/// Fitted Gaussian NaiveBayes parameters, by label.
+#[derive(Debug, Clone, PartialEq)]
+struct Model {
+    priors: Vec<f64>,
+    means: Vec<Vec<f64>>,
+    variances: Vec<Vec<f64>>,
+}
 
+/// Plays 3-player games with a Gaussian NaiveBayes classifier that maps the encoded
+/// observation to one of the `Action::all_3players` labels.
 #[derive(Debug)]
 pub struct AIPlayer {
-    classifier: NaiveBayes<Gaussian>,
+    model: Option<Model>,
     name: String,
     coins: usize,
     hand: Vec<Character>,
-    index: usize,
 }
 
 impl AIPlayer {
-    pub fn new(name: String, index: usize) -> AIPlayer {
+    pub fn new(name: String) -> AIPlayer {
         AIPlayer {
-            classifier: NaiveBayes::<Gaussian>::new(),
+            model: None,
             name,
             coins: 2,
             hand: Vec::new(),
-            index,
         }
     }
-}
 
-impl Player for AIPlayer {
-    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
-        let action_space = Action::all_3players(0,1,2);
-        let num_actions = action_space.len();
-
-        let mut features = Matrix::zeros(num_actions, possible_actions.len());
+    /// Creates a player from a model file written by [`AIPlayer::save`].
+    pub fn load<P: AsRef<Path>>(name: String, path: P) -> Result<AIPlayer, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let lines: Vec<String> = BufReader::new(file).lines().collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+        if lines.first().map(|l| l.as_str()) != Some(FILE_HEADER) {
+            return Err("Not a NaiveBayes model file".to_string());
+        }
+        let num_features = lines.get(1).and_then(|l| l.strip_prefix("features ")).ok_or("line 2: expected 'features'")?;
+        let num_features: usize = num_features.trim().parse().map_err(|e| format!("line 2: {}", e))?;
+        if num_features != GameStateAI::feature_len(3) {
+            return Err(format!("Model has {} features, a 3-player game has {}", num_features, GameStateAI::feature_len(3)));
+        }
+        let num_labels = AIPlayer::num_labels();
+        if lines.len() != 2 + num_labels {
+            return Err(format!("Expected {} labels, found {}", num_labels, lines.len().saturating_sub(2)));
+        }
 
-        for (i, action) in action_space.iter().enumerate() {
-            for (j, possible_action) in possible_actions.iter().enumerate() {
-                if *action == *possible_action {
-                    features[[i, j]] = 1.0;
-                }
+        let mut model = Model { priors: Vec::new(), means: Vec::new(), variances: Vec::new() };
+        for (i, line) in lines.iter().enumerate().skip(2) {
+            let row = line.split_whitespace().map(|x| x.parse::<f64>()).collect::<Result<Vec<f64>, _>>().map_err(|e| format!("line {}: {}", i + 1, e))?;
+            if row.len() != 1 + 2 * num_features {
+                return Err(format!("line {}: expected {} values, found {}", i + 1, 1 + 2 * num_features, row.len()));
+            }
+            if row[1 + num_features..].iter().any(|&v| v <= 0.0) {
+                return Err(format!("line {}: variances must be positive", i + 1));
             }
+            model.priors.push(row[0]);
+            model.means.push(row[1..1 + num_features].to_vec());
+            model.variances.push(row[1 + num_features..].to_vec());
         }
+        let mut player = AIPlayer::new(name);
+        player.model = Some(model);
+        Ok(player)
+    }
 
-        let probs = self.classifier.predict(&features).unwrap().into_vec();
+    /// Writes the number of features, then the fitted parameters with one line per label:
+    /// the prior, then the feature means, then the feature variances.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let model = self.model.as_ref().ok_or("Model has not been trained")?;
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+        writeln!(out, "{}", FILE_HEADER).map_err(|e| e.to_string())?;
+        writeln!(out, "features {}", model.means.first().map_or(0, |m| m.len())).map_err(|e| e.to_string())?;
+        for label in 0..model.priors.len() {
+            let values: Vec<String> = std::iter::once(&model.priors[label])
+                .chain(model.means[label].iter())
+                .chain(model.variances[label].iter())
+                .map(|v| v.to_string())
+                .collect();
+            writeln!(out, "{}", values.join(" ")).map_err(|e| e.to_string())?;
+        }
+        out.flush().map_err(|e| e.to_string())
+    }
 
-        let mut max_prob = 0.0;
-        let mut best_action = &action_space[0];
+    /// The label space for the player in seat `player_index`.
+    pub fn labels(player_index: usize) -> Vec<Action> {
+        ActionSpace::new(3).actions(player_index)
+    }
 
-        for (i, prob) in probs.iter().enumerate() {
-            if *prob > max_prob {
-                max_prob = *prob;
-                best_action = &action_space[i];
-            }
-        }
-    
-        best_action.clone()
+    pub fn encode_label(action: &Action, player_index: usize) -> Option<usize> {
+        ActionSpace::new(3).encode(action, player_index)
     }
 
-    fn choose_card(&self, _game_state: &GameState) -> Character {
-        let cards = self.hand();
-        //display all cards in the hand in one line
-        print!("{}: Your cards are: ", self.name);
-        for (i, card) in cards.iter().enumerate() {
-            print!("{}. {}, ", i, card);
+    pub fn decode_label(label: usize, player_index: usize) -> Option<Action> {
+        ActionSpace::new(3).decode(label, player_index)
+    }
+
+    pub fn num_labels() -> usize {
+        ActionSpace::new(3).len()
+    }
+
+    pub fn is_trained(&self) -> bool {
+        self.model.is_some()
+    }
+
+    /// Fits the classifier on `dataset`, whose samples are `GameStateAI::features` of a
+    /// 3-player game labelled with `AIPlayer::encode_label`. Labels that never occur get
+    /// a prior of 0 and are never predicted.
+    pub fn train(&mut self, dataset: &Dataset) -> Result<(), String> {
+        if dataset.is_empty() {
+            return Err("Cannot train on an empty dataset".to_string());
         }
-        println!();
-        //read number from user
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input).unwrap();
-        let input = input.trim();
-        let mut result = input.parse::<usize>().unwrap();
-        //return the card
-        while result >= cards.len() {
-            println!("Invalid input, please try again");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).unwrap();
-            let input = input.trim();
-            result = input.parse::<usize>().unwrap();
+        let num_features = dataset.feature_len()?;
+        if num_features != GameStateAI::feature_len(3) {
+            return Err("AIPlayer only plays 3-player games".to_string());
         }
-        cards[result]
-    }
-
-    fn possible_actions(&self, game_state: &GameState) -> Vec<Action> {
-        let mut actions = vec![];
-        let number_of_players = game_state.players.len();
-        match game_state.phase {
-            crate::phase::Phase::Action => {
-                let coins = self.coins();
-                if coins >= 7 {
-                    for i in 0..number_of_players {
-                        if i != game_state.current_player {
-                            actions.push(Action::Coup(i));
-                        }
-                    }
-                }
-                if coins < 10 {
-                    if coins >= 3 {
-                        for i in 0..number_of_players {
-                            if i != game_state.current_player {
-                                actions.push(Action::Assassinate(i));
-                            }
-                        }
-                    }
-                    for i in 0..number_of_players {
-                        if i != game_state.current_player {
-                            actions.push(Action::Steal(i, game_state.players[i].coins().min(2)));
-                        }
-                    }
-                    actions.push(Action::ForeignAid);
-                    actions.push(Action::Income);
-                    actions.push(Action::Exchange);
-                }
-            }
-            crate::phase::Phase::Block => {
-                let index = game_state.players.iter().position(|p| p.name() == self.name()).unwrap();
-                let mut search = game_state.history.len() - 1;
-                while !game_state.history[search].0.is_action() {
-                    search -= 1;
-                }
-                let (action, player_index) = &game_state.history[search];
-
-                match action {
-                    Action::Assassinate(self_index) if *self_index == index => {
-                        //if contessa is in hand
-                        if self.hand().iter().any(|c| c == &Character::Contessa) {
-                            actions.push(Action::Block(crate::action::BlockAction::Assassinate(*self_index), game_state.current_player, Some(Character::Contessa)));
-                        } else {
-                            actions.push(Action::Block(crate::action::BlockAction::Assassinate(*self_index), game_state.current_player, None));
-                        }
-                    }
-                    Action::Steal(self_index, amount) if *self_index == index => {
-                        if self.hand().contains(&Character::Captain) || self.hand().contains(&Character::Ambassador) {
-                            if self.hand().contains(&Character::Captain) {
-                                actions.push(Action::Block(crate::action::BlockAction::Stealing(*self_index, *amount), game_state.current_player, Some(Character::Captain)));
-                            }
-                            if self.hand().contains(&Character::Ambassador) {
-                                actions.push(Action::Block(crate::action::BlockAction::Stealing(*self_index, *amount), game_state.current_player, Some(Character::Ambassador)));
-                            }
-                        } else {
-                            actions.push(Action::Block(crate::action::BlockAction::Stealing(*self_index, *amount), game_state.current_player, None));
-                        }
-                    }
-                    Action::ForeignAid => {
-                        if self.hand().contains(&Character::Duke) {
-                            actions.push(Action::Block(crate::action::BlockAction::ForeignAid(game_state.current_player), game_state.current_player, Some(Character::Duke)));
-                        } else {
-                            actions.push(Action::Block(crate::action::BlockAction::ForeignAid(game_state.current_player), game_state.current_player, None));
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            crate::phase::Phase::Challenge => {
-                let mut search = game_state.history.len() - 1;
-                while !game_state.history[search].0.is_action_or_block() {
-                    search -= 1;
-                }
-                let (action, player_index) = &game_state.history[search];
-                let player_index = *player_index;
-                match action {
-                    Action::Block(block_action, player, char) => {
-                        match block_action {
-                            crate::action::BlockAction::Assassinate(from) => {
-                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockAssassination(*from), player_index));
-                            }
-                            crate::action::BlockAction::Stealing(from, amount) => {
-                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockStealing(*from, *amount), player_index));
-                            }
-                            crate::action::BlockAction::ForeignAid(from) => {
-                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockForeignAid(*from), player_index));
-                            }
-                        }
-                    }
-                    Action::Exchange => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Exchange, player_index));
-                    }
-                    Action::Steal(target, amount) => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Stealing(*target, *amount), player_index));
-                    }
-                    Action::Assassinate(target) => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Assassination(*target), player_index));
-                    }
-                    Action::Tax => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Tax, player_index));
-                    }
-                    _ => {}
+        let num_labels = AIPlayer::num_labels();
+        if let Some(sample) = dataset.samples.iter().find(|s| s.label >= num_labels) {
+            return Err(format!("Label {} is outside the action space", sample.label));
+        }
+
+        let mut model = Model { priors: Vec::new(), means: Vec::new(), variances: Vec::new() };
+        for label in 0..num_labels {
+            let rows: Vec<&Vec<f64>> = dataset.samples.iter().filter(|s| s.label == label).map(|s| &s.features).collect();
+            let count = rows.len().max(1) as f64;
+            let mean: Vec<f64> = (0..num_features).map(|j| rows.iter().map(|r| r[j]).sum::<f64>() / count).collect();
+            let variance: Vec<f64> = (0..num_features)
+                .map(|j| rows.iter().map(|r| (r[j] - mean[j]).powi(2)).sum::<f64>() / count + VARIANCE_EPSILON)
+                .collect();
+            model.priors.push(rows.len() as f64 / dataset.len() as f64);
+            model.means.push(mean);
+            model.variances.push(variance);
+        }
+        self.model = Some(model);
+        Ok(())
+    }
+
+    /// Log-probability of every label for the observation. Fails if the player is
+    /// untrained or the observation is not from a 3-player game.
+    pub fn label_log_probs(&self, game_state: &GameStateAI) -> Result<Vec<f64>, String> {
+        let model = self.model.as_ref().ok_or("Model has not been trained")?;
+        let features = game_state.features();
+        let num_features = model.means.first().map_or(0, |m| m.len());
+        if features.len() != num_features {
+            return Err(format!("Expected {} features, found {}", num_features, features.len()));
+        }
+        let joint: Vec<f64> = (0..model.priors.len()).map(|label| {
+            let likelihood: f64 = features.iter().zip(model.means[label].iter().zip(model.variances[label].iter()))
+                .map(|(x, (mean, variance))| -0.5 * (2.0 * std::f64::consts::PI * variance).ln() - (x - mean).powi(2) / (2.0 * variance))
+                .sum();
+            model.priors[label].ln() + likelihood
+        }).collect();
+        //normalise with log-sum-exp
+        let max = joint.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let total = max + joint.iter().map(|j| (j - max).exp()).sum::<f64>().ln();
+        Ok(joint.iter().map(|j| j - total).collect())
+    }
+}
+
+impl Player for AIPlayer {
+    /// Picks the legal action with the highest predicted probability. An untrained
+    /// player, or one seated at a table of another size, takes the first legal action.
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        let log_probs = match self.label_log_probs(game_state) {
+            Ok(log_probs) => log_probs,
+            Err(_) => return possible_actions[0].clone(),
+        };
+
+        let mut best_action = &possible_actions[0];
+        let mut max_prob = f64::NEG_INFINITY;
+        for action in possible_actions.iter() {
+            if let Some(label) = AIPlayer::encode_label(action, game_state.player_index) {
+                let prob = log_probs[label];
+                if prob.is_finite() && prob > max_prob {
+                    max_prob = prob;
+                    best_action = action;
                 }
             }
         }
-        actions
+
+        best_action.clone()
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        least_valuable_card(&self.hand)
     }
 
     fn name(&self) -> String {
@@ -234,4 +241,32 @@ impl Player for AIPlayer {
             Err("Card not found in hand".to_string())
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::AIPlayer;
+    use crate::player::spec::AgentSpec;
+    use crate::ruleset::Ruleset;
+    use crate::selfplay;
+
+    #[test]
+    fn saved_model_loads_back_unchanged() {
+        let dataset = selfplay::play_game(&[AgentSpec::Random, AgentSpec::Random, AgentSpec::Random], &Ruleset::classic(), 3).unwrap();
+        let mut player = AIPlayer::new("nb".to_string());
+        player.train(&dataset).unwrap();
+        let path = std::env::temp_dir().join(format!("coup-nb-{}.txt", std::process::id()));
+        player.save(&path).unwrap();
+        let loaded = AIPlayer::load("nb".to_string(), &path);
+
+        //a model for another feature length is refused
+        let text = std::fs::read_to_string(&path).unwrap();
+        let (header, rest) = text.split_once('\n').unwrap();
+        let (_, rows) = rest.split_once('\n').unwrap();
+        std::fs::write(&path, format!("{}\nfeatures 5\n{}", header, rows)).unwrap();
+        let mismatched = AIPlayer::load("nb".to_string(), &path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.unwrap().model, player.model);
+        assert!(mismatched.is_err());
+    }
+}
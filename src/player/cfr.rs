@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::cfr::{policy, Strategy};
+use crate::character::Character;
+use crate::game::GameState;
+use crate::action::Action;
+use crate::gameai::GameStateAI;
+
+use super::Player;
+use super::heuristic::{least_valuable_card, HeuristicPlayer, Personality};
+
+/// Plays the average strategy learned by `CfrSolver`, sampling among the options of
+/// the current information set. Information sets the strategy does not cover (e.g.
+/// past the solver's turn limit) are played by an honest heuristic player.
+#[derive(Debug)]
+pub struct CfrPlayer {
+    strategy: Rc<Strategy>,
+    fallback: HeuristicPlayer,
+    rng: RefCell<StdRng>,
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+}
+
+impl CfrPlayer {
+    pub fn new(name: &str, strategy: Rc<Strategy>, seed: u64) -> CfrPlayer {
+        CfrPlayer {
+            strategy,
+            fallback: HeuristicPlayer::new(name, Personality::honest(), seed.wrapping_add(1)),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            name: name.to_string(),
+            coins: 2,
+            hand: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(name: &str, path: P, seed: u64) -> Result<CfrPlayer, String> {
+        Ok(CfrPlayer::new(name, Rc::new(Strategy::load(path)?), seed))
+    }
+}
+
+impl Player for CfrPlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        if possible_actions.len() == 1 {
+            return possible_actions[0].clone();
+        }
+        let probabilities = policy(&self.strategy, &self.fallback, game_state, &possible_actions);
+        let mut x: f64 = self.rng.borrow_mut().gen();
+        for (action, p) in possible_actions.iter().zip(probabilities.iter()) {
+            if x < *p {
+                return action.clone();
+            }
+            x -= p;
+        }
+        possible_actions[possible_actions.len() - 1].clone()
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        least_valuable_card(&self.hand)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
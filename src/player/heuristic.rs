@@ -0,0 +1,428 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::belief::BluffModel;
+use crate::character::Character;
+use crate::game::GameState;
+use crate::action::Action;
+use crate::gameai::GameStateAI;
+use crate::opponent::OpponentModel;
+use crate::ruleset::ActionKind;
+
+use super::Player;
+
+/// Tunable behaviour of a [`HeuristicPlayer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Personality {
+    /// Probability of claiming a character the player does not hold on a given turn or block.
+    pub bluff_rate: f64,
+    /// Base probability of challenging a claim that is not provably false.
+    pub challenge_rate: f64,
+}
+
+impl Personality {
+    /// Never bluffs and rarely challenges.
+    pub fn honest() -> Personality {
+        Personality { bluff_rate: 0.0, challenge_rate: 0.1 }
+    }
+
+    /// Bluffs often and challenges freely.
+    pub fn aggressive_bluffer() -> Personality {
+        Personality { bluff_rate: 0.6, challenge_rate: 0.3 }
+    }
+
+    /// Rarely bluffs and only challenges when the visible cards make a bluff likely.
+    pub fn cautious_challenger() -> Personality {
+        Personality { bluff_rate: 0.1, challenge_rate: 0.05 }
+    }
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Personality::honest()
+    }
+}
+
+fn card_value(card: Character) -> usize {
+    match card {
+        Character::Duke => 5,
+        Character::Assassin => 4,
+        Character::Captain => 3,
+        Character::Contessa => 2,
+        Character::Ambassador => 1,
+        Character::Inquisitor => 1,
+        Character::Socialist => 4,
+        Character::Speculator => 4,
+        Character::Reporter => 2,
+    }
+}
+
+/// The card a rule-based player gives up first: a duplicate if there is one,
+/// otherwise the least useful character.
+pub fn least_valuable_card(hand: &[Character]) -> Character {
+    for card in hand.iter() {
+        if hand.iter().filter(|&c| c == card).count() > 1 {
+            return *card;
+        }
+    }
+    *hand.iter().min_by_key(|&&c| card_value(c)).expect("choose_card called with an empty hand")
+}
+
+/// Rule-based bot: claims what it holds, bluffs with the personality's bluff rate,
+/// challenges according to how many copies of the claimed character it can see,
+/// and directs Coup, Assassinate and Steal at the most threatening opponent.
+/// Given an [`OpponentModel`], it challenges known bluffers more and bluffs more
+/// against tables that rarely challenge.
+#[derive(Debug)]
+pub struct HeuristicPlayer {
+    personality: Personality,
+    opponents: Option<Arc<OpponentModel>>,
+    rng: RefCell<StdRng>,
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+}
+
+impl HeuristicPlayer {
+    pub fn new(name: &str, personality: Personality, seed: u64) -> HeuristicPlayer {
+        HeuristicPlayer {
+            personality,
+            opponents: None,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            name: name.to_string(),
+            coins: 2,
+            hand: Vec::new(),
+        }
+    }
+
+    pub fn with_opponent_model(mut self, opponents: Arc<OpponentModel>) -> HeuristicPlayer {
+        self.opponents = Some(opponents);
+        self
+    }
+
+    pub fn personality(&self) -> Personality {
+        self.personality
+    }
+
+    /// The personality's bluff rate, scaled by how much less (or more) than usual the
+    /// most eager challenger still in the game challenges.
+    fn bluff_rate(&self, game_state: &GameStateAI) -> f64 {
+        let opponents = match &self.opponents {
+            Some(opponents) => opponents,
+            None => return self.personality.bluff_rate,
+        };
+        let challenge_rate = (0..game_state.names.len())
+            .filter(|&i| i != game_state.player_index && game_state.players_lives[i] > 0)
+            .map(|i| opponents.bluff_model(&game_state.names[i]).challenge_rate)
+            .fold(0.0, f64::max);
+        (self.personality.bluff_rate * BluffModel::default().challenge_rate / challenge_rate.max(0.01)).min(0.9)
+    }
+
+    /// The personality's challenge rate, scaled by how much more (or less) than usual
+    /// the claimant has been caught bluffing.
+    fn challenge_rate(&self, game_state: &GameStateAI, claimant: usize) -> f64 {
+        match &self.opponents {
+            Some(opponents) => {
+                let bluff_rate = opponents.bluff_model(&game_state.names[claimant]).bluff_rate;
+                self.personality.challenge_rate * bluff_rate / BluffModel::default().bluff_rate
+            },
+            None => self.personality.challenge_rate,
+        }
+    }
+
+    fn roll(&self, probability: f64) -> bool {
+        self.rng.borrow_mut().gen_bool(probability.clamp(0.0, 1.0))
+    }
+
+    /// Whether this player holds a character that allows `kind` under the rules in play.
+    fn holds_claimant(game_state: &GameStateAI, kind: ActionKind) -> bool {
+        game_state.rules.claimants(kind).iter().any(|c| game_state.cards_in_hand.contains(c))
+    }
+
+    /// Copies of `character` this player can see: its own hand plus revealed cards.
+    fn visible_copies(game_state: &GameStateAI, character: Character) -> usize {
+        game_state.cards_in_hand.iter().filter(|&&c| c == character).count()
+            + game_state.revealed_characters.iter().filter(|&&c| c == Some(character)).count()
+    }
+
+    /// Copies of `character` in the deck of the rules in play.
+    fn deck_copies(game_state: &GameStateAI, character: Character) -> usize {
+        game_state.rules.deck().iter().filter(|&&c| c == character).count()
+    }
+
+    fn threat(game_state: &GameStateAI, player: usize) -> usize {
+        game_state.players_lives[player] * 10 + game_state.players_coins[player]
+    }
+
+    fn close_to_winning(game_state: &GameStateAI, player: usize) -> bool {
+        let me = game_state.player_index;
+        let opponents = (0..game_state.players_lives.len()).filter(|&i| i != me && !game_state.is_teammate(i) && game_state.players_lives[i] > 0).count();
+        opponents == 1 || game_state.players_coins[player] >= game_state.rules.config.coup_cost || game_state.players_lives[player] > game_state.players_lives[me]
+    }
+
+    /// Picks the action from `candidates` aimed at the most threatening target, sparing
+    /// teammates if friendly fire leaves a choice.
+    fn most_threatening<'a>(game_state: &GameStateAI, candidates: impl Iterator<Item = &'a Action>) -> Option<Action> {
+        candidates
+            .filter_map(|a| a.target().map(|t| (a, t)))
+            .max_by_key(|(_, t)| (!game_state.is_teammate(*t), Self::threat(game_state, *t)))
+            .map(|(a, _)| a.clone())
+    }
+
+    fn choose_turn_action(&self, actions: &[Action], game_state: &GameStateAI) -> Action {
+        let bluffing = self.roll(self.bluff_rate(game_state));
+        let can_claim = |kind: ActionKind| bluffing || Self::holds_claimant(game_state, kind);
+
+        let coups = actions.iter().filter(|a| matches!(a, Action::Coup(_)));
+        let forced = actions.iter().all(|a| matches!(a, Action::Coup(_)));
+        let threatened = actions.iter().any(|a| match a {
+            Action::Coup(target) => Self::close_to_winning(game_state, *target),
+            _ => false,
+        });
+        if forced || threatened {
+            if let Some(coup) = Self::most_threatening(game_state, coups) {
+                return coup;
+            }
+        }
+
+        //fund a teammate's Coup if we cannot afford one ourselves
+        let coup_cost = game_state.rules.config.coup_cost;
+        if game_state.players_coins[game_state.player_index] < coup_cost {
+            let gift = actions.iter().find(|a| match a {
+                Action::Give(teammate, coins) => game_state.players_coins[*teammate] < coup_cost && game_state.players_coins[*teammate] + coins >= coup_cost,
+                _ => false,
+            });
+            if let Some(gift) = gift {
+                return gift.clone();
+            }
+        }
+
+        if can_claim(ActionKind::Assassinate) {
+            if let Some(assassinate) = Self::most_threatening(game_state, actions.iter().filter(|a| matches!(a, Action::Assassinate(_)))) {
+                return assassinate;
+            }
+        }
+        if game_state.rules.proves(&game_state.cards_in_hand, &Action::Embezzle) && game_state.treasury >= 3 && actions.contains(&Action::Embezzle) {
+            return Action::Embezzle;
+        }
+        if can_claim(ActionKind::Tax) && actions.contains(&Action::Tax) {
+            return Action::Tax;
+        }
+        let me = game_state.player_index;
+        let coins = game_state.players_coins[me];
+        if can_claim(ActionKind::Speculate) && coins >= 2 && actions.contains(&Action::Speculate) {
+            return Action::Speculate;
+        }
+        let richer = (0..game_state.players_coins.len()).filter(|&i| game_state.players_lives[i] > 0 && game_state.players_coins[i] > coins).count();
+        if can_claim(ActionKind::Redistribute) && richer >= 2 && actions.contains(&Action::Redistribute) {
+            return Action::Redistribute;
+        }
+        if can_claim(ActionKind::Steal) {
+            let steal = actions.iter()
+                .filter_map(|a| match a {
+                    Action::Steal(target, amount) if *amount > 0 => Some((a, *amount, Self::threat(game_state, *target))),
+                    _ => None,
+                })
+                .max_by_key(|(_, amount, threat)| (*amount, *threat));
+            if let Some((steal, _, _)) = steal {
+                return steal.clone();
+            }
+        }
+        let has_power = [ActionKind::Tax, ActionKind::Assassinate, ActionKind::Steal, ActionKind::Redistribute, ActionKind::Speculate]
+            .into_iter()
+            .any(|kind| Self::holds_claimant(game_state, kind));
+        if Self::holds_claimant(game_state, ActionKind::Report) && !has_power && actions.contains(&Action::Report) {
+            return Action::Report;
+        }
+        if Self::holds_claimant(game_state, ActionKind::Exchange) && !has_power && actions.contains(&Action::Exchange) {
+            return Action::Exchange;
+        }
+        //Foreign Aid is safe once two thirds of the copies of its blockers are accounted for
+        let unblocked = game_state.rules.blockers(ActionKind::ForeignAid).into_iter()
+            .all(|c| 3 * Self::visible_copies(game_state, c) >= 2 * Self::deck_copies(game_state, c));
+        if unblocked && actions.contains(&Action::ForeignAid) {
+            return Action::ForeignAid;
+        }
+        if actions.contains(&Action::Income) {
+            return Action::Income;
+        }
+        actions[0].clone()
+    }
+
+    fn choose_response(&self, actions: &[Action], game_state: &GameStateAI) -> Action {
+        let me = game_state.player_index;
+        let last_life = game_state.players_lives[me] == 1;
+
+        if let Some((target, card)) = game_state.examined {
+            //take a strong card away from the examined opponent
+            if card_value(card) >= 3 && actions.contains(&Action::ForceExchange(target)) {
+                return Action::ForceExchange(target);
+            }
+        }
+        //block with a character in hand, or bluff the one with fewest copies in sight
+        let blocks: Vec<&Action> = actions.iter().filter(|a| matches!(a, Action::Block(_, _, _))).collect();
+        if let Some(honest) = blocks.iter().find(|a| game_state.rules.proves(&game_state.cards_in_hand, a)) {
+            return (*honest).clone();
+        }
+        let bluff = blocks.into_iter().min_by_key(|a| game_state.rules.claimed(a).iter().map(|&c| Self::visible_copies(game_state, c)).sum::<usize>());
+        if let Some(action @ Action::Block(block_action, _, _)) = bluff {
+            let desperate = last_life && matches!(block_action, crate::action::BlockAction::Assassinate(_));
+            if desperate || self.roll(self.bluff_rate(game_state)) {
+                return action.clone();
+            }
+        }
+
+        for action in actions {
+            if let Action::Challenge(challenge_action, claimant) = action {
+                if game_state.is_teammate(*claimant) {
+                    continue;
+                }
+                //the claim is a bluff for sure once every copy of what it names is in sight
+                let claimed = game_state.rules.challenged(challenge_action);
+                let copies: usize = claimed.iter().map(|&c| Self::deck_copies(game_state, c)).sum();
+                let visible: usize = claimed.iter().map(|&c| Self::visible_copies(game_state, c).min(Self::deck_copies(game_state, c))).sum();
+                if !claimed.is_empty() && visible >= copies {
+                    return action.clone();
+                }
+                let desperate = last_life
+                    && matches!(challenge_action, crate::action::ChallengeAction::Assassination(target) if *target == me)
+                    && !game_state.rules.blockers(ActionKind::Assassinate).iter().any(|c| game_state.cards_in_hand.contains(c));
+                if desperate || self.roll(self.challenge_rate(game_state, *claimant) * (visible + 1) as f64 / copies.max(1) as f64) {
+                    return action.clone();
+                }
+            }
+        }
+
+        Action::Pass
+    }
+}
+
+impl Player for HeuristicPlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        if possible_actions.iter().any(|a| a.is_action()) {
+            self.choose_turn_action(&possible_actions, game_state)
+        } else {
+            self.choose_response(&possible_actions, game_state)
+        }
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        least_valuable_card(&self.hand)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeuristicPlayer, Personality};
+    use crate::action::{Action, ChallengeAction};
+    use crate::character::Character;
+    use crate::game::GameState;
+    use crate::gameai::GameStateAI;
+    use crate::player::Player;
+    use crate::player::scripted::ScriptedPlayer;
+    use crate::ruleset::Ruleset;
+
+    /// What seat 0 sees at a three-player table where it holds `hand`.
+    fn observe(hand: &[Character], rules: Ruleset) -> GameStateAI {
+        let players: Vec<Box<dyn Player>> = [hand, &[Character::Captain, Character::Ambassador], &[Character::Captain, Character::Ambassador]].iter().map(|cards| {
+            let mut player = ScriptedPlayer::new("scripted", vec![], vec![]);
+            for &card in cards.iter() {
+                player.add_card_to_hand(card);
+            }
+            Box::new(player) as Box<dyn Player>
+        }).collect();
+        let mut game = GameState::new(players);
+        game.verbose = false;
+        game.set_rules(rules);
+        GameStateAI::from_gamestate(&game, 0)
+    }
+
+    /// How many of 50 seeded players of `personality` pick `choice` from `options`.
+    fn picks(personality: Personality, state: &GameStateAI, options: &[Action], choice: &Action) -> usize {
+        (0..50).filter(|&seed| HeuristicPlayer::new("heuristic", personality, seed).choose_action(options.to_vec(), state) == *choice).count()
+    }
+
+    fn with_dukes(copies: usize) -> Ruleset {
+        let mut rules = Ruleset::classic();
+        rules.characters.iter_mut().find(|c| c.character == Character::Duke).unwrap().copies = copies;
+        rules
+    }
+
+    #[test]
+    fn honest_players_never_claim_what_they_lack() {
+        let state = observe(&[Character::Contessa, Character::Contessa], Ruleset::classic());
+        let options = [Action::Income, Action::ForeignAid, Action::Tax];
+        assert_eq!(picks(Personality::honest(), &state, &options, &Action::Tax), 0);
+        assert_eq!(picks(Personality::honest(), &state, &options, &Action::Income), 50);
+    }
+
+    #[test]
+    fn aggressive_bluffers_claim_what_they_lack() {
+        let state = observe(&[Character::Contessa, Character::Contessa], Ruleset::classic());
+        let options = [Action::Income, Action::ForeignAid, Action::Tax];
+        let bluffs = picks(Personality::aggressive_bluffer(), &state, &options, &Action::Tax);
+        assert!(bluffs > 15 && bluffs < 50, "{} bluffs", bluffs);
+    }
+
+    #[test]
+    fn cautious_challengers_count_the_copies_in_the_deck() {
+        let options = [Action::Challenge(ChallengeAction::Tax, 1), Action::Pass];
+        let challenge = &options[0];
+        //both Dukes of a two-Duke deck are in hand, so the Tax is a bluff
+        let state = observe(&[Character::Duke, Character::Duke], with_dukes(2));
+        assert_eq!(picks(Personality::cautious_challenger(), &state, &options, challenge), 50);
+        //with four Dukes one is still out there
+        let state = observe(&[Character::Duke, Character::Duke], with_dukes(4));
+        assert!(picks(Personality::cautious_challenger(), &state, &options, challenge) < 25);
+    }
+
+    #[test]
+    fn foreign_aid_waits_for_the_blockers_to_be_seen() {
+        let options = [Action::Income, Action::ForeignAid];
+        let state = observe(&[Character::Duke, Character::Duke], Ruleset::classic());
+        assert_eq!(picks(Personality::cautious_challenger(), &state, &options, &Action::ForeignAid), 50);
+        let state = observe(&[Character::Duke, Character::Duke], with_dukes(4));
+        assert_eq!(picks(Personality::cautious_challenger(), &state, &options, &Action::ForeignAid), 0);
+    }
+}
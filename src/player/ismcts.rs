@@ -0,0 +1,440 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::belief::BeliefState;
+use crate::character::Character;
+use crate::game::GameState;
+use crate::action::Action;
+use crate::gameai::GameStateAI;
+use crate::opponent::OpponentModel;
+
+use super::Player;
+use super::heuristic::{least_valuable_card, HeuristicPlayer, Personality};
+use super::random::RandomPlayer;
+
+/// How much searching an [`IsmctsPlayer`] does per decision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchBudget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+/// Policy used to finish a simulated game once it leaves the search tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rollout {
+    Random,
+    Heuristic,
+}
+
+/// Information-Set Monte Carlo Tree Search (single observer).
+///
+/// Every iteration samples opponents' hands from a [`BeliefState`] built from the public
+/// events, consistent with what this player can see (its own hand, the revealed cards
+/// and the deck of the roles in play),
+/// rebuilds the game at the current point of the turn and steps the engine forward.
+/// Decisions of all players are taken from a shared tree over public actions while
+/// inside it, and by the rollout policy after it. The move with the best average reward
+/// among those tried wins.
+#[derive(Debug)]
+pub struct IsmctsPlayer {
+    budget: SearchBudget,
+    rollout: Rollout,
+    exploration: f64,
+    max_rollout_steps: usize,
+    opponents: Option<Arc<OpponentModel>>,
+    rng: RefCell<StdRng>,
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+}
+
+impl IsmctsPlayer {
+    pub fn new(name: &str, budget: SearchBudget, seed: u64) -> IsmctsPlayer {
+        IsmctsPlayer {
+            budget,
+            rollout: Rollout::Heuristic,
+            exploration: 0.7,
+            max_rollout_steps: 500,
+            opponents: None,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            name: name.to_string(),
+            coins: 2,
+            hand: Vec::new(),
+        }
+    }
+
+    pub fn with_rollout(mut self, rollout: Rollout) -> IsmctsPlayer {
+        self.rollout = rollout;
+        self
+    }
+
+    pub fn with_exploration(mut self, exploration: f64) -> IsmctsPlayer {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Simulated games still running after this many engine steps are scored by the
+    /// share of remaining influence each player holds.
+    pub fn with_max_rollout_steps(mut self, steps: usize) -> IsmctsPlayer {
+        self.max_rollout_steps = steps;
+        self
+    }
+
+    /// Reads opponents' hands with the bluff and challenge rates recorded for their names.
+    pub fn with_opponent_model(mut self, opponents: Arc<OpponentModel>) -> IsmctsPlayer {
+        self.opponents = Some(opponents);
+        self
+    }
+
+    /// Deals opponents hands drawn from `belief` and the deck from the remaining cards
+    /// this player cannot see, and rebuilds the game at the observed point of the turn
+    /// with tree-guided players in every seat.
+    fn determinize(&self, game_state: &GameStateAI, belief: &BeliefState, tree: &Rc<RefCell<SearchTree>>) -> GameState {
+        let mut rng = self.rng.borrow_mut();
+        let mut hands = belief.sample_hands(&mut *rng);
+        pin_examined(&mut hands, game_state);
+        let mut unseen = game_state.rules.deck();
+        let opponents_hands = hands.iter().enumerate().filter(|(seat, _)| *seat != game_state.player_index).flat_map(|(_, h)| h.iter());
+        for card in game_state.cards_in_hand.iter().chain(game_state.revealed_characters.iter().flatten()).chain(opponents_hands) {
+            if let Some(index) = unseen.iter().position(|c| c == card) {
+                unseen.remove(index);
+            }
+        }
+        unseen.shuffle(&mut *rng);
+
+        let mut players: Vec<Box<dyn Player>> = Vec::new();
+        for (seat, hand) in hands.iter().enumerate() {
+            let name = format!("sim{}", seat);
+            let mut inner: Box<dyn Player> = match self.rollout {
+                Rollout::Random => Box::new(RandomPlayer::new(&name, rng.gen())),
+                Rollout::Heuristic => Box::new(HeuristicPlayer::new(&name, Personality::honest(), rng.gen())),
+            };
+            let _ = inner.lose_coins(inner.coins());
+            inner.add_coins(game_state.players_coins[seat]);
+            if seat == game_state.player_index {
+                for card in game_state.cards_in_hand.iter() {
+                    inner.add_card_to_hand(*card);
+                }
+            } else {
+                for card in hand.iter().take(game_state.players_lives[seat]) {
+                    inner.add_card_to_hand(*card);
+                }
+                for _ in hand.len()..game_state.players_lives[seat] {
+                    if let Some(card) = unseen.pop() {
+                        inner.add_card_to_hand(card);
+                    }
+                }
+            }
+            players.push(Box::new(TreePlayer { seat, inner, tree: Rc::clone(tree) }));
+        }
+
+        let mut game = GameState::new(players);
+        game.verbose = false;
+        game.rng = StdRng::seed_from_u64(rng.gen());
+        game.deck = unseen;
+        game.revealed_characters = game_state.revealed_characters.clone();
+        game.events = game_state.events.clone();
+        game.current_player = game_state.current_player;
+        game.phase = game_state.phase;
+        game.turn = game_state.turn.clone();
+        game.factions = game_state.factions.clone();
+        game.treasury = game_state.treasury;
+        game.rules = game_state.rules.clone();
+        game.teams = game_state.teams.clone();
+        game.tie_break = game_state.tie_break;
+        game.eliminations = game_state.eliminations.clone();
+        game.quiet_since = game_state.quiet_since;
+        if let Some(action) = &game_state.turn.action {
+            game.history.push((action.clone(), game_state.current_player));
+        }
+        if let Some(block) = &game_state.turn.block {
+            game.history.push(block.clone());
+        }
+        game
+    }
+
+    fn simulate(&self, game_state: &GameStateAI, belief: &BeliefState, tree: &Rc<RefCell<SearchTree>>) {
+        tree.borrow_mut().begin_iteration();
+        let mut game = self.determinize(game_state, belief, tree);
+        let mut steps = 0;
+        while !game.game_over() && steps < self.max_rollout_steps {
+            game.step();
+            steps += 1;
+        }
+
+        let mut lives: Vec<f64> = game.players.iter().map(|p| p.hand().len() as f64).collect();
+        let total: f64 = lives.iter().sum();
+        if let Some(teams) = &game.teams {
+            //teammates share the influence their team has left
+            let own = lives.clone();
+            lives = (0..own.len()).map(|i| (0..own.len()).filter(|&j| teams.same_team(i, j)).map(|j| own[j]).sum()).collect();
+        }
+        let rewards: Vec<f64> = lives.iter().map(|l| if total > 0.0 { l / total } else { 0.0 }).collect();
+        let rewards = if game.game_over() {
+            (0..rewards.len()).map(|i| if game.has_won(i) { 1.0 } else { 0.0 }).collect()
+        } else {
+            rewards
+        };
+        tree.borrow_mut().backpropagate(&rewards);
+    }
+}
+
+/// Puts the card this player is examining into the target's sampled hand, whatever the
+/// belief drew, and hands the card it replaces to whoever was dealt that copy instead.
+fn pin_examined(hands: &mut [Vec<Character>], game_state: &GameStateAI) {
+    let (target, card) = match game_state.examined {
+        Some((target, card)) if target != game_state.player_index => (target, card),
+        _ => return,
+    };
+    if hands[target].contains(&card) {
+        return;
+    }
+    let swapped = match hands[target].first_mut() {
+        Some(slot) => std::mem::replace(slot, card),
+        None => {
+            hands[target].push(card);
+            return;
+        },
+    };
+    //if nobody else holds that copy it was still unseen, and the swapped card goes back there
+    let holder = (0..hands.len())
+        .filter(|&seat| seat != target && seat != game_state.player_index)
+        .find_map(|seat| hands[seat].iter().position(|&c| c == card).map(|slot| (seat, slot)));
+    if let Some((seat, slot)) = holder {
+        hands[seat][slot] = swapped;
+    }
+}
+
+impl Player for IsmctsPlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        if possible_actions.len() == 1 {
+            return possible_actions[0].clone();
+        }
+
+        let seed = self.rng.borrow_mut().gen();
+        let tree = Rc::new(RefCell::new(SearchTree::new(game_state.player_index, self.exploration, seed)));
+        let mut belief = BeliefState::for_rules(game_state.players_lives.len(), &game_state.rules);
+        belief.observe_hand(game_state.player_index, &game_state.cards_in_hand);
+        if let Some(opponents) = &self.opponents {
+            for (seat, name) in game_state.names.iter().enumerate() {
+                belief.set_model(seat, opponents.bluff_model(name));
+            }
+        }
+        belief.sync(&game_state.events);
+        let started = Instant::now();
+        let mut iterations = 0;
+        loop {
+            let done = match self.budget {
+                SearchBudget::Iterations(n) => iterations >= n,
+                SearchBudget::Time(limit) => started.elapsed() >= limit,
+            };
+            if done {
+                break;
+            }
+            self.simulate(game_state, &belief, &tree);
+            iterations += 1;
+        }
+
+        let tree = tree.borrow();
+        tree.nodes[0].children.iter()
+            .filter(|child| child.visits > 0.0 && possible_actions.contains(&child.action))
+            .max_by(|a, b| (a.reward / a.visits).total_cmp(&(b.reward / b.visits)))
+            .map(|child| child.action.clone())
+            .unwrap_or_else(|| possible_actions[0].clone())
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        least_valuable_card(&self.hand)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Child {
+    action: Action,
+    node: usize,
+    visits: f64,
+    /// How often this action was legal when its parent was visited.
+    availability: f64,
+    /// Sum of rewards for the player who took the action.
+    reward: f64,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: Vec<Child>,
+}
+
+/// Tree over public action sequences starting at the searching player's decision.
+#[derive(Debug)]
+struct SearchTree {
+    nodes: Vec<Node>,
+    searcher: usize,
+    exploration: f64,
+    rng: StdRng,
+    /// Node of the next decision, or None until the searcher has made its root decision.
+    cursor: Option<usize>,
+    in_tree: bool,
+    path: Vec<(usize, usize, usize)>,
+}
+
+impl SearchTree {
+    fn new(searcher: usize, exploration: f64, seed: u64) -> SearchTree {
+        SearchTree {
+            nodes: vec![Node::default()],
+            searcher,
+            exploration,
+            rng: StdRng::seed_from_u64(seed),
+            cursor: None,
+            in_tree: true,
+            path: Vec::new(),
+        }
+    }
+
+    fn begin_iteration(&mut self) {
+        self.cursor = None;
+        self.in_tree = true;
+        self.path.clear();
+    }
+
+    /// Picks the tree's move for `seat`, or None once the simulation has left the tree
+    /// (or, before the root, for players answering the same window as the searcher).
+    fn select(&mut self, seat: usize, options: &[Action]) -> Option<Action> {
+        if !self.in_tree {
+            return None;
+        }
+        let node = match self.cursor {
+            Some(node) => node,
+            None if seat == self.searcher => 0,
+            None => return None,
+        };
+
+        for child in self.nodes[node].children.iter_mut() {
+            if options.contains(&child.action) {
+                child.availability += 1.0;
+            }
+        }
+
+        let untried: Vec<&Action> = options.iter().filter(|a| !self.nodes[node].children.iter().any(|c| c.action == **a)).collect();
+        let index = if let Some(&action) = untried.choose(&mut self.rng) {
+            let new_node = self.nodes.len();
+            self.nodes.push(Node::default());
+            self.nodes[node].children.push(Child { action: action.clone(), node: new_node, visits: 0.0, availability: 1.0, reward: 0.0 });
+            self.in_tree = false;
+            self.nodes[node].children.len() - 1
+        } else {
+            let exploration = self.exploration;
+            self.nodes[node].children.iter().enumerate()
+                .filter(|(_, c)| options.contains(&c.action))
+                .map(|(i, c)| (i, c.reward / c.visits + exploration * (c.availability.ln() / c.visits).sqrt()))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)?
+        };
+
+        self.path.push((node, index, seat));
+        let child = &self.nodes[node].children[index];
+        self.cursor = Some(child.node);
+        Some(child.action.clone())
+    }
+
+    fn backpropagate(&mut self, rewards: &[f64]) {
+        for &(node, index, seat) in self.path.iter() {
+            let child = &mut self.nodes[node].children[index];
+            child.visits += 1.0;
+            child.reward += rewards[seat];
+        }
+    }
+}
+
+/// Seat in a simulated game: takes moves from the search tree while it can and
+/// otherwise plays like the rollout policy it wraps.
+struct TreePlayer {
+    seat: usize,
+    inner: Box<dyn Player>,
+    tree: Rc<RefCell<SearchTree>>,
+}
+
+impl Player for TreePlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        let selected = self.tree.borrow_mut().select(self.seat, &possible_actions);
+        match selected {
+            Some(action) => action,
+            None => self.inner.choose_action(possible_actions, game_state),
+        }
+    }
+
+    fn choose_card(&self, game_state: &GameState) -> Character {
+        self.inner.choose_card(game_state)
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn coins(&self) -> usize {
+        self.inner.coins()
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.inner.add_coins(coins)
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        self.inner.lose_coins(coins)
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.inner.hand()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.inner.add_card_to_hand(card)
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        self.inner.remove_card_from_hand(card)
+    }
+}
@@ -1,21 +1,174 @@
 use std::fmt::Debug;
 
+use crate::belief::BeliefState;
 use crate::character::Character;
 use crate::game::GameState;
-use crate::action::Action;
+use crate::action::{Action, BlockAction, GIFTS};
 use crate::gameai::GameStateAI;
+use crate::ruleset::ActionKind;
 
 pub mod ai;
+pub mod cfr;
+pub mod heuristic;
+pub mod ismcts;
+pub mod neural;
+pub mod random;
+pub mod scripted;
+pub mod spec;
 
 pub trait Player {
     fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action;
-    fn possible_actions(&self, game_state: &GameState) -> Vec<Action>;
+    /// The moves open to this player, sitting in seat `index`, in the current phase.
+    fn possible_actions(&self, game_state: &GameState, index: usize) -> Vec<Action> {
+        let mut actions = vec![];
+        let number_of_players = game_state.players.len();
+        match game_state.phase {
+            crate::phase::Phase::Action => {
+                let rules = &game_state.rules;
+                let coins = self.coins();
+                let me = game_state.current_player;
+                let targets: Vec<usize> = (0..number_of_players)
+                    .filter(|&i| i != me && !game_state.is_eliminated(i) && game_state.may_target(me, i))
+                    .collect();
+                if coins >= rules.config.coup_cost {
+                    actions.extend(targets.iter().map(|&i| Action::Coup(i)));
+                }
+                if coins < rules.config.forced_coup {
+                    if coins >= rules.config.assassinate_cost && rules.enables(ActionKind::Assassinate) {
+                        actions.extend(targets.iter().map(|&i| Action::Assassinate(i)));
+                    }
+                    if rules.enables(ActionKind::Steal) {
+                        actions.extend(targets.iter().map(|&i| Action::Steal(i, game_state.players[i].coins().min(rules.config.steal))));
+                    }
+                    for (kind, action) in [(ActionKind::ForeignAid, Action::ForeignAid), (ActionKind::Tax, Action::Tax), (ActionKind::Income, Action::Income), (ActionKind::Exchange, Action::Exchange)] {
+                        if rules.enables(kind) {
+                            actions.push(action);
+                        }
+                    }
+                    if rules.enables(ActionKind::Examine) {
+                        actions.extend(targets.iter().map(|&i| Action::Examine(i)));
+                    }
+                    for (kind, action) in [(ActionKind::Redistribute, Action::Redistribute), (ActionKind::Speculate, Action::Speculate), (ActionKind::Report, Action::Report)] {
+                        if rules.enables(kind) {
+                            actions.push(action);
+                        }
+                    }
+                    if game_state.factions.is_some() {
+                        if coins >= 1 {
+                            actions.push(Action::Convert(me));
+                        }
+                        if coins >= 2 {
+                            for i in 0..number_of_players {
+                                if i != me && !game_state.is_eliminated(i) {
+                                    actions.push(Action::Convert(i));
+                                }
+                            }
+                        }
+                        if game_state.treasury > 0 {
+                            actions.push(Action::Embezzle);
+                        }
+                    }
+                    if let Some(teams) = game_state.teams.as_ref().filter(|t| t.giving) {
+                        for gift in GIFTS.into_iter().filter(|&g| g <= coins) {
+                            for i in 0..number_of_players {
+                                if i != me && !game_state.is_eliminated(i) && teams.same_team(me, i) {
+                                    actions.push(Action::Give(i, gift));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            crate::phase::Phase::Block => {
+                let mut search = game_state.history.len() - 1;
+                while !game_state.history[search].0.is_action() {
+                    search -= 1;
+                }
+                let (action, _player_index) = &game_state.history[search];
+
+                let blocked = match action {
+                    Action::Assassinate(self_index) if *self_index == index => Some((ActionKind::Assassinate, BlockAction::Assassinate(*self_index))),
+                    Action::Steal(self_index, amount) if *self_index == index => Some((ActionKind::Steal, BlockAction::Stealing(*self_index, *amount))),
+                    Action::ForeignAid if game_state.may_target(index, game_state.current_player) => Some((ActionKind::ForeignAid, BlockAction::ForeignAid(game_state.current_player))),
+                    _ => None,
+                };
+                //anyone may claim any blocker, truthfully or not; a challenge checks that one
+                if let Some((kind, block_action)) = blocked {
+                    for character in game_state.rules.blockers(kind) {
+                        actions.push(Action::Block(block_action, game_state.current_player, character));
+                    }
+                }
+            }
+            crate::phase::Phase::Challenge => {
+                let mut search = game_state.history.len() - 1;
+                while !game_state.history[search].0.is_action_or_block() {
+                    search -= 1;
+                }
+                let (action, player_index) = &game_state.history[search];
+                let player_index = *player_index;
+                if !game_state.rules.is_claim(action) {
+                    return actions;
+                }
+                match action {
+                    Action::Block(block_action, _player, _character) => {
+                        match block_action {
+                            crate::action::BlockAction::Assassinate(from) => {
+                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockAssassination(*from), player_index));
+                            }
+                            crate::action::BlockAction::Stealing(from, amount) => {
+                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockStealing(*from, *amount), player_index));
+                            }
+                            crate::action::BlockAction::ForeignAid(from) => {
+                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockForeignAid(*from), player_index));
+                            }
+                        }
+                    }
+                    Action::Exchange => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Exchange, player_index));
+                    }
+                    Action::Steal(target, amount) => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Stealing(*target, *amount), player_index));
+                    }
+                    Action::Assassinate(target) => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Assassination(*target), player_index));
+                    }
+                    Action::Tax => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Tax, player_index));
+                    }
+                    Action::Embezzle => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Embezzle, player_index));
+                    }
+                    Action::Examine(target) => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Examine(*target), player_index));
+                    }
+                    Action::Redistribute => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Redistribute, player_index));
+                    }
+                    Action::Speculate => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Speculate, player_index));
+                    }
+                    Action::Report => {
+                        actions.push(Action::Challenge(crate::action::ChallengeAction::Report, player_index));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        actions
+    }
+
     fn name(&self) -> String;
     fn coins(&self) -> usize;
     fn add_coins(&mut self, coins: usize);
     fn lose_coins(&mut self, coins: usize) -> Result<(), String>;
     fn hand(&self) -> Vec<Character>;
+    /// The card to give up when losing an influence, or to show when examined.
     fn choose_card(&self, game_state: &GameState) -> Character;
+    /// The card to put back in the deck after drawing more than the hand keeps: in the
+    /// draft, an Exchange or a Report. By default the card `choose_card` would give up.
+    fn choose_return(&self, game_state: &GameState) -> Character {
+        self.choose_card(game_state)
+    }
     fn add_card_to_hand(&mut self, card: Character);
     fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String>;
 }
@@ -38,7 +191,24 @@ impl HumanPlayer {
 }
 
 impl Player for HumanPlayer {
-    fn choose_action(&self, actions: Vec<Action>, _game_state: &GameStateAI) -> Action {
+    fn choose_action(&self, actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        //show what the opponents probably hold
+        let mut belief = BeliefState::for_rules(game_state.players_lives.len(), &game_state.rules);
+        belief.observe_hand(game_state.player_index, &game_state.cards_in_hand);
+        belief.sync(&game_state.events);
+        for (player, &lives) in game_state.players_lives.iter().enumerate() {
+            if player == game_state.player_index || lives == 0 {
+                continue;
+            }
+            print!("Player {} likely holds: ", player);
+            for character in game_state.rules.characters() {
+                print!("{} {:.0}%, ", character, 100.0 * belief.probability(player, character));
+            }
+            println!();
+        }
+        if let Some((target, card)) = game_state.examined {
+            println!("Player {} shows you a {}.", target, card);
+        }
         //display all possible actions in one line
         let coins = self.coins();
         print!("{}: You have {} coins. Possible actions are: ", self.name, coins);
@@ -86,116 +256,6 @@ impl Player for HumanPlayer {
         cards[result]
     }
 
-    fn possible_actions(&self, game_state: &GameState) -> Vec<Action> {
-        let mut actions = vec![];
-        let number_of_players = game_state.players.len();
-        match game_state.phase {
-            crate::phase::Phase::Action => {
-                let coins = self.coins();
-                if coins >= 7 {
-                    for i in 0..number_of_players {
-                        if i != game_state.current_player {
-                            actions.push(Action::Coup(i));
-                        }
-                    }
-                }
-                if coins < 10 {
-                    if coins >= 3 {
-                        for i in 0..number_of_players {
-                            if i != game_state.current_player {
-                                actions.push(Action::Assassinate(i));
-                            }
-                        }
-                    }
-                    for i in 0..number_of_players {
-                        if i != game_state.current_player {
-                            actions.push(Action::Steal(i, game_state.players[i].coins().min(2)));
-                        }
-                    }
-                    actions.push(Action::ForeignAid);
-                    actions.push(Action::Income);
-                    actions.push(Action::Exchange);
-                }
-            }
-            crate::phase::Phase::Block => {
-                let index = game_state.players.iter().position(|p| p.name() == self.name()).unwrap();
-                let mut search = game_state.history.len() - 1;
-                while !game_state.history[search].0.is_action() {
-                    search -= 1;
-                }
-                let (action, _player_index) = &game_state.history[search];
-
-                match action {
-                    Action::Assassinate(self_index) if *self_index == index => {
-                        //if contessa is in hand
-                        if self.hand().iter().any(|c| c == &Character::Contessa) {
-                            actions.push(Action::Block(crate::action::BlockAction::Assassinate(*self_index), game_state.current_player, Some(Character::Contessa)));
-                        } else {
-                            actions.push(Action::Block(crate::action::BlockAction::Assassinate(*self_index), game_state.current_player, None));
-                        }
-                    }
-                    Action::Steal(self_index, amount) if *self_index == index => {
-                        if self.hand().contains(&Character::Captain) || self.hand().contains(&Character::Ambassador) {
-                            if self.hand().contains(&Character::Captain) {
-                                actions.push(Action::Block(crate::action::BlockAction::Stealing(*self_index, *amount), game_state.current_player, Some(Character::Captain)));
-                            }
-                            if self.hand().contains(&Character::Ambassador) {
-                                actions.push(Action::Block(crate::action::BlockAction::Stealing(*self_index, *amount), game_state.current_player, Some(Character::Ambassador)));
-                            }
-                        } else {
-                            actions.push(Action::Block(crate::action::BlockAction::Stealing(*self_index, *amount), game_state.current_player, None));
-                        }
-                    }
-                    Action::ForeignAid => {
-                        if self.hand().contains(&Character::Duke) {
-                            actions.push(Action::Block(crate::action::BlockAction::ForeignAid(game_state.current_player), game_state.current_player, Some(Character::Duke)));
-                        } else {
-                            actions.push(Action::Block(crate::action::BlockAction::ForeignAid(game_state.current_player), game_state.current_player, None));
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            crate::phase::Phase::Challenge => {
-                let mut search = game_state.history.len() - 1;
-                while !game_state.history[search].0.is_action_or_block() {
-                    search -= 1;
-                }
-                let (action, player_index) = &game_state.history[search];
-                let player_index = *player_index;
-                match action {
-                    Action::Block(block_action, _player, _char) => {
-                        match block_action {
-                            crate::action::BlockAction::Assassinate(from) => {
-                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockAssassination(*from), player_index));
-                            }
-                            crate::action::BlockAction::Stealing(from, amount) => {
-                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockStealing(*from, *amount), player_index));
-                            }
-                            crate::action::BlockAction::ForeignAid(from) => {
-                                actions.push(Action::Challenge(crate::action::ChallengeAction::BlockForeignAid(*from), player_index));
-                            }
-                        }
-                    }
-                    Action::Exchange => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Exchange, player_index));
-                    }
-                    Action::Steal(target, amount) => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Stealing(*target, *amount), player_index));
-                    }
-                    Action::Assassinate(target) => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Assassination(*target), player_index));
-                    }
-                    Action::Tax => {
-                        actions.push(Action::Challenge(crate::action::ChallengeAction::Tax, player_index));
-                    }
-                    _ => {}
-                }
-            }
-        }
-        actions
-    }
-
     fn name(&self) -> String {
         self.name.clone()
     }
@@ -0,0 +1,434 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rusty_machine::learning::nnet::{Criterion, NeuralNet};
+use rusty_machine::learning::optim::{OptimAlgorithm, Optimizable};
+use rusty_machine::learning::toolkit::activ_fn::Sigmoid;
+use rusty_machine::learning::toolkit::cost_fn::CostFunc;
+use rusty_machine::linalg::Matrix;
+use rusty_machine::prelude::*;
+
+use crate::action::{Action, BlockAction, ChallengeAction};
+use crate::action_space::ActionSpace;
+use crate::character::Character;
+use crate::config::GameConfig;
+use crate::dataset::Dataset;
+use crate::game::GameState;
+use crate::gameai::GameStateAI;
+
+use super::Player;
+use super::heuristic::least_valuable_card;
+
+/// First line of a saved policy network.
+const FILE_HEADER: &str = "coup-policy-net 1";
+
+/// Settings for supervised training of a [`PolicyNet`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingConfig {
+    /// Passes of mini-batch gradient descent over the data.
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    pub momentum: f64,
+    /// Drives the order samples are visited in.
+    pub seed: u64,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        TrainingConfig { epochs: 20, batch_size: 32, learning_rate: 0.1, momentum: 0.9, seed: 0 }
+    }
+}
+
+/// Feed-forward network from `GameStateAI::features` to one sigmoid output per
+/// [`ActionSpace`] index. Inference and training go through rusty-machine's
+/// `NeuralNet`; the weights are kept here because the network borrows its layer sizes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyNet {
+    pub num_players: usize,
+    /// Input, hidden and output layer sizes.
+    pub layers: Vec<usize>,
+    /// Every layer's weights, bias row first, in the order `NeuralNet` lays them out.
+    pub weights: Vec<f64>,
+}
+
+impl PolicyNet {
+    /// A network with the given hidden layers and weights drawn from `seed`.
+    pub fn new(num_players: usize, hidden: &[usize], seed: u64) -> PolicyNet {
+        let mut layers = vec![GameStateAI::feature_len(num_players)];
+        layers.extend_from_slice(hidden);
+        layers.push(ActionSpace::new(num_players).len());
+
+        // Same uniform initialisation as `NeuralNet`, but reproducible.
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut weights = Vec::new();
+        for pair in layers.windows(2) {
+            let (inputs, outputs) = (pair[0] + 1, pair[1]);
+            let eps = (6.0 / (inputs + outputs) as f64).sqrt();
+            weights.extend((0..inputs * outputs).map(|_| rng.gen_range(-eps..eps)));
+        }
+        PolicyNet { num_players, layers, weights }
+    }
+
+    /// Output for every action index given the encoded observation.
+    pub fn outputs(&self, features: &[f64]) -> Result<Vec<f64>, String> {
+        Ok(self.outputs_batch(&[features.to_vec()])?.remove(0))
+    }
+
+    /// [`PolicyNet::outputs`] for many observations in one pass.
+    pub fn outputs_batch(&self, features: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, String> {
+        let inputs = self.matrix(features)?;
+        let mut net = NeuralNet::new(&self.layers, PolicyCriterion, Keep(self.weights.clone()));
+        net.train(&inputs, &Matrix::zeros(inputs.rows(), self.num_outputs())).map_err(|e| e.to_string())?;
+        let outputs = net.predict(&inputs).map_err(|e| e.to_string())?;
+        Ok(outputs.into_vec().chunks(self.num_outputs()).map(|row| row.to_vec()).collect())
+    }
+
+    /// Softmax over the logits of the legal outputs; illegal actions get 0.
+    pub fn probabilities(&self, features: &[f64], legal: &[bool]) -> Result<Vec<f64>, String> {
+        Ok(masked_softmax(&self.outputs(features)?, legal))
+    }
+
+    /// One step of gradient descent that moves every output towards its target, with
+    /// the cross-entropy gradient `output - target` averaged over the rows.
+    pub fn descend(&mut self, features: &[Vec<f64>], targets: &[Vec<f64>], learning_rate: f64) -> Result<(), String> {
+        let inputs = self.matrix(features)?;
+        if targets.len() != features.len() || targets.iter().any(|t| t.len() != self.num_outputs()) {
+            return Err("Every row needs one target per output".to_string());
+        }
+        let targets = Matrix::new(targets.len(), self.num_outputs(), targets.concat());
+        let config = TrainingConfig { epochs: 1, batch_size: features.len(), learning_rate, momentum: 0.0, seed: 0 };
+        self.fit(&inputs, &targets, &config)
+    }
+
+    /// Imitation learning: fits the outputs to the one-hot label of every sample, so the
+    /// network learns to pick the moves recorded in `dataset`.
+    pub fn train(&mut self, dataset: &Dataset, config: &TrainingConfig) -> Result<(), String> {
+        if dataset.is_empty() {
+            return Err("Cannot train on an empty dataset".to_string());
+        }
+        let num_features = dataset.feature_len()?;
+        let num_actions = self.layers[self.layers.len() - 1];
+        if num_features != self.layers[0] {
+            return Err(format!("Samples have {} features but the network expects {}", num_features, self.layers[0]));
+        }
+        if let Some(sample) = dataset.samples.iter().find(|s| s.label >= num_actions) {
+            return Err(format!("Label {} is outside the action space", sample.label));
+        }
+        if config.learning_rate <= 0.0 {
+            return Err("The learning rate must be positive".to_string());
+        }
+
+        let mut inputs = Vec::new();
+        let mut targets = Matrix::zeros(dataset.len(), num_actions);
+        for (i, sample) in dataset.samples.iter().enumerate() {
+            inputs.extend_from_slice(&sample.features);
+            targets[[i, sample.label]] = 1.0;
+        }
+        let inputs = Matrix::new(dataset.len(), num_features, inputs);
+
+        self.fit(&inputs, &targets, config)
+    }
+
+    fn fit(&mut self, inputs: &Matrix<f64>, targets: &Matrix<f64>, config: &TrainingConfig) -> Result<(), String> {
+        let descent = Descent { layers: self.layers.clone(), start: self.weights.clone(), config: config.clone() };
+        let mut net = NeuralNet::new(&self.layers, PolicyCriterion, descent);
+        net.train(inputs, targets).map_err(|e| e.to_string())?;
+        self.weights = (0..self.layers.len() - 1).flat_map(|l| net.get_net_weights(l).iter().copied().collect::<Vec<_>>()).collect();
+        Ok(())
+    }
+
+    fn num_outputs(&self) -> usize {
+        self.layers[self.layers.len() - 1]
+    }
+
+    fn matrix(&self, features: &[Vec<f64>]) -> Result<Matrix<f64>, String> {
+        if features.is_empty() || features.iter().any(|f| f.len() != self.layers[0]) {
+            return Err(format!("Expected rows of {} features", self.layers[0]));
+        }
+        Ok(Matrix::new(features.len(), self.layers[0], features.concat()))
+    }
+
+    /// Writes the player count, the layer sizes and then one line of weights per layer.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut out = BufWriter::new(file);
+        writeln!(out, "{}", FILE_HEADER).map_err(|e| e.to_string())?;
+        writeln!(out, "players {}", self.num_players).map_err(|e| e.to_string())?;
+        let layers: Vec<String> = self.layers.iter().map(|l| l.to_string()).collect();
+        writeln!(out, "layers {}", layers.join(" ")).map_err(|e| e.to_string())?;
+        let mut start = 0;
+        for pair in self.layers.windows(2) {
+            let end = start + (pair[0] + 1) * pair[1];
+            let weights: Vec<String> = self.weights[start..end].iter().map(|w| w.to_string()).collect();
+            writeln!(out, "{}", weights.join(" ")).map_err(|e| e.to_string())?;
+            start = end;
+        }
+        out.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PolicyNet, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let lines: Vec<String> = BufReader::new(file).lines().collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+        if lines.first().map(|l| l.as_str()) != Some(FILE_HEADER) {
+            return Err("Not a policy network file".to_string());
+        }
+        let field = |i: usize, name: &str| -> Result<Vec<usize>, String> {
+            let line = lines.get(i).and_then(|l| l.strip_prefix(name)).ok_or_else(|| format!("line {}: expected '{}'", i + 1, name))?;
+            line.split_whitespace().map(|x| x.parse::<usize>().map_err(|e| format!("line {}: {}", i + 1, e))).collect()
+        };
+        let num_players = *field(1, "players ")?.first().ok_or("line 2: missing player count")?;
+        let layers = field(2, "layers ")?;
+        if !(2..=6).contains(&num_players) {
+            return Err(format!("Unsupported player count {}", num_players));
+        }
+        if layers.len() < 2 || layers[0] != GameStateAI::feature_len(num_players) || layers[layers.len() - 1] != ActionSpace::new(num_players).len() {
+            return Err(format!("Layer sizes do not fit a {}-player game", num_players));
+        }
+
+        let mut weights = Vec::new();
+        for (l, pair) in layers.windows(2).enumerate() {
+            let line = lines.get(3 + l).ok_or_else(|| format!("missing weights for layer {}", l))?;
+            let row = line.split_whitespace().map(|w| w.parse::<f64>()).collect::<Result<Vec<f64>, _>>().map_err(|e| format!("line {}: {}", l + 4, e))?;
+            if row.len() != (pair[0] + 1) * pair[1] {
+                return Err(format!("line {}: expected {} weights, found {}", l + 4, (pair[0] + 1) * pair[1], row.len()));
+            }
+            weights.extend(row);
+        }
+        Ok(PolicyNet { num_players, layers, weights })
+    }
+}
+
+/// Softmax over the logits of the `legal` entries of sigmoid `outputs`; the rest get 0.
+pub fn masked_softmax(outputs: &[f64], legal: &[bool]) -> Vec<f64> {
+    let logits: Vec<f64> = outputs.iter().map(|&p| {
+        let p = p.clamp(1e-12, 1.0 - 1e-12);
+        (p / (1.0 - p)).ln()
+    }).collect();
+    let max = logits.iter().zip(legal.iter()).filter(|(_, &l)| l).map(|(&x, _)| x).fold(f64::NEG_INFINITY, f64::max);
+    let exp: Vec<f64> = logits.iter().zip(legal.iter()).map(|(&x, &l)| if l { (x - max).exp() } else { 0.0 }).collect();
+    let total: f64 = exp.iter().sum();
+    exp.iter().map(|e| if total > 0.0 { e / total } else { 0.0 }).collect()
+}
+
+/// Sigmoid units with cross-entropy cost, like `BCECriterion`, except that the cost
+/// gradient stays finite once an output saturates to exactly 0 or 1.
+#[derive(Debug, Clone, Copy)]
+struct PolicyCriterion;
+
+impl Criterion for PolicyCriterion {
+    type ActFunc = Sigmoid;
+    type Cost = ClampedCrossEntropy;
+}
+
+/// Smallest distance from 0 and 1 that outputs are clamped to when computing the cost.
+const OUTPUT_EPSILON: f64 = 1e-7;
+
+struct ClampedCrossEntropy;
+
+impl CostFunc<Matrix<f64>> for ClampedCrossEntropy {
+    fn cost(outputs: &Matrix<f64>, targets: &Matrix<f64>) -> f64 {
+        let outputs = outputs.clone().apply(&|p: f64| p.clamp(OUTPUT_EPSILON, 1.0 - OUTPUT_EPSILON));
+        let total: f64 = outputs.iter().zip(targets.iter()).map(|(&p, &t)| t * p.ln() + (1.0 - t) * (1.0 - p).ln()).sum();
+        -total / outputs.rows() as f64
+    }
+
+    fn grad_cost(outputs: &Matrix<f64>, targets: &Matrix<f64>) -> Matrix<f64> {
+        let outputs = outputs.clone().apply(&|p: f64| p.clamp(OUTPUT_EPSILON, 1.0 - OUTPUT_EPSILON));
+        (&outputs - targets).elediv(&outputs.elemul(&(-&outputs + 1.0)))
+    }
+}
+
+/// Leaves the weights at the given values, which turns `NeuralNet::train` into a setter.
+struct Keep(Vec<f64>);
+
+impl<M: Optimizable> OptimAlgorithm<M> for Keep {
+    fn optimize(&self, _model: &M, _start: &[f64], _inputs: &M::Inputs, _targets: &M::Targets) -> Vec<f64> {
+        self.0.clone()
+    }
+}
+
+/// Mini-batch gradient descent with momentum, starting from given weights.
+///
+/// rusty-machine 0.5 returns each layer's gradient as an outputs x inputs matrix while
+/// it stores that layer's weights inputs x outputs, so its own optimizers step the
+/// wrong weights whenever a layer is not square. This one transposes every layer's
+/// gradient back first.
+struct Descent {
+    layers: Vec<usize>,
+    start: Vec<f64>,
+    config: TrainingConfig,
+}
+
+impl Descent {
+    /// `gradient` laid out like the weights.
+    fn reorder(&self, gradient: Vec<f64>) -> Vec<f64> {
+        let mut reordered = Vec::with_capacity(gradient.len());
+        let mut start = 0;
+        for pair in self.layers.windows(2) {
+            let (inputs, outputs) = (pair[0] + 1, pair[1]);
+            for i in 0..inputs {
+                reordered.extend((0..outputs).map(|o| gradient[start + o * inputs + i]));
+            }
+            start += inputs * outputs;
+        }
+        reordered
+    }
+}
+
+impl<M: Optimizable<Inputs = Matrix<f64>, Targets = Matrix<f64>>> OptimAlgorithm<M> for Descent {
+    fn optimize(&self, model: &M, _start: &[f64], inputs: &Matrix<f64>, targets: &Matrix<f64>) -> Vec<f64> {
+        let mut weights = self.start.clone();
+        let mut velocity = vec![0.0; weights.len()];
+        let mut order: Vec<usize> = (0..inputs.rows()).collect();
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        for _ in 0..self.config.epochs {
+            order.shuffle(&mut rng);
+            for batch in order.chunks(self.config.batch_size.max(1)) {
+                let (_, gradient) = model.compute_grad(&weights, &inputs.select_rows(batch), &targets.select_rows(batch));
+                for ((w, v), g) in weights.iter_mut().zip(velocity.iter_mut()).zip(self.reorder(gradient)) {
+                    *v = self.config.momentum * *v - self.config.learning_rate * g;
+                    *w += *v;
+                }
+            }
+        }
+        weights
+    }
+}
+
+/// Plays the legal move with the highest output of a [`PolicyNet`], or samples from
+/// its [`PolicyNet::probabilities`] once `with_sampling` is set.
+#[derive(Debug)]
+pub struct NeuralPlayer {
+    policy: PolicyNet,
+    space: ActionSpace,
+    sampling: Option<RefCell<StdRng>>,
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+}
+
+impl NeuralPlayer {
+    pub fn new(name: &str, policy: PolicyNet) -> NeuralPlayer {
+        NeuralPlayer {
+            space: ActionSpace::new(policy.num_players),
+            sampling: None,
+            policy,
+            name: name.to_string(),
+            coins: 2,
+            hand: Vec::new(),
+        }
+    }
+
+    pub fn with_sampling(mut self, seed: u64) -> Self {
+        self.sampling = Some(RefCell::new(StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Creates a player from a network written by [`PolicyNet::save`].
+    pub fn load<P: AsRef<Path>>(name: &str, path: P) -> Result<NeuralPlayer, String> {
+        Ok(NeuralPlayer::new(name, PolicyNet::load(path)?))
+    }
+
+    /// Output of the network that stands for `action`. The network only knows Steals of
+    /// up to the official amount, so a larger Steal allowed by house rules counts as the
+    /// largest one it knows.
+    fn index(&self, action: &Action, player: usize) -> Option<usize> {
+        let cap = GameConfig::default().steal;
+        let capped = match action {
+            Action::Steal(target, coins) => Action::Steal(*target, (*coins).min(cap)),
+            Action::Block(BlockAction::Stealing(from, coins), player, character) => Action::Block(BlockAction::Stealing(*from, (*coins).min(cap)), *player, *character),
+            Action::Challenge(ChallengeAction::Stealing(target, coins), player) => Action::Challenge(ChallengeAction::Stealing(*target, (*coins).min(cap)), *player),
+            Action::Challenge(ChallengeAction::BlockStealing(from, coins), player) => Action::Challenge(ChallengeAction::BlockStealing(*from, (*coins).min(cap)), *player),
+            _ => action.clone(),
+        };
+        self.space.encode(&capped, player)
+    }
+}
+
+impl Player for NeuralPlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        if possible_actions.len() == 1 || game_state.players_lives.len() != self.policy.num_players {
+            return possible_actions[0].clone();
+        }
+        let outputs = match self.policy.outputs(&game_state.features()) {
+            Ok(outputs) => outputs,
+            Err(_) => return possible_actions[0].clone(),
+        };
+        if let Some(rng) = &self.sampling {
+            let mut legal = vec![false; self.space.len()];
+            for index in possible_actions.iter().filter_map(|a| self.index(a, game_state.player_index)) {
+                legal[index] = true;
+            }
+            let probabilities = masked_softmax(&outputs, &legal);
+            let mut x: f64 = rng.borrow_mut().gen();
+            for action in possible_actions.iter() {
+                let p = self.index(action, game_state.player_index).map(|i| probabilities[i]).unwrap_or(0.0);
+                if x < p {
+                    return action.clone();
+                }
+                x -= p;
+            }
+            return possible_actions[possible_actions.len() - 1].clone();
+        }
+
+        let mut best_action = &possible_actions[0];
+        let mut best = f64::NEG_INFINITY;
+        for action in possible_actions.iter() {
+            if let Some(index) = self.index(action, game_state.player_index) {
+                if outputs[index] > best {
+                    best = outputs[index];
+                    best_action = action;
+                }
+            }
+        }
+        best_action.clone()
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        least_valuable_card(&self.hand)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
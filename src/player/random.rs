@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::character::Character;
+use crate::game::GameState;
+use crate::action::Action;
+use crate::gameai::GameStateAI;
+
+use super::Player;
+
+/// Baseline agent that picks uniformly among its legal moves. Seeded so that
+/// games between random players can be reproduced.
+#[derive(Debug)]
+pub struct RandomPlayer {
+    rng: RefCell<StdRng>,
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+}
+
+impl RandomPlayer {
+    pub fn new(name: &str, seed: u64) -> RandomPlayer {
+        RandomPlayer {
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            name: name.to_string(),
+            coins: 2,
+            hand: Vec::new(),
+        }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, _game_state: &GameStateAI) -> Action {
+        possible_actions.choose(&mut *self.rng.borrow_mut()).cloned().unwrap_or(Action::Pass)
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        *self.hand.choose(&mut *self.rng.borrow_mut()).expect("choose_card called with an empty hand")
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use crate::character::Character;
+use crate::game::GameState;
+use crate::action::Action;
+use crate::gameai::GameStateAI;
+
+use super::Player;
+
+/// Replays a fixed list of decisions, for deterministic engine fixtures.
+///
+/// Actions and discarded cards are consumed in order. Once the action script runs
+/// out the player passes when it can and otherwise takes its first legal move; once
+/// the card script runs out it gives up the first card in hand.
+#[derive(Debug)]
+pub struct ScriptedPlayer {
+    actions: RefCell<VecDeque<Action>>,
+    cards: RefCell<VecDeque<Character>>,
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+}
+
+impl ScriptedPlayer {
+    pub fn new(name: &str, actions: Vec<Action>, cards: Vec<Character>) -> ScriptedPlayer {
+        ScriptedPlayer {
+            actions: RefCell::new(actions.into()),
+            cards: RefCell::new(cards.into()),
+            name: name.to_string(),
+            coins: 2,
+            hand: Vec::new(),
+        }
+    }
+
+    /// Number of scripted actions not yet played.
+    pub fn remaining_actions(&self) -> usize {
+        self.actions.borrow().len()
+    }
+}
+
+impl Player for ScriptedPlayer {
+    fn choose_action(&self, possible_actions: Vec<Action>, _game_state: &GameStateAI) -> Action {
+        match self.actions.borrow_mut().pop_front() {
+            Some(action) => {
+                if !possible_actions.contains(&action) {
+                    panic!("{}: scripted action {} is not legal here, expected one of {:?}", self.name, action, possible_actions);
+                }
+                action
+            },
+            None => {
+                if possible_actions.contains(&Action::Pass) {
+                    Action::Pass
+                } else {
+                    possible_actions[0].clone()
+                }
+            },
+        }
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        match self.cards.borrow_mut().pop_front() {
+            Some(card) => {
+                if !self.hand.contains(&card) {
+                    panic!("{}: scripted card {} is not in hand {:?}", self.name, card, self.hand);
+                }
+                card
+            },
+            None => self.hand[0],
+        }
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::opponent::OpponentModel;
+
+use super::Player;
+use super::ai::AIPlayer;
+use super::cfr::CfrPlayer;
+use super::heuristic::{HeuristicPlayer, Personality};
+use super::ismcts::{IsmctsPlayer, SearchBudget};
+use super::neural::NeuralPlayer;
+use super::random::RandomPlayer;
+
+/// Description of a non-human agent, as given on the command line:
+/// `random`, `heuristic[:honest|aggressive|cautious]`, `ai:<model file>` or
+/// `ismcts[:<iterations>|:<milliseconds>ms]`, `cfr:<strategy file>` or `nn:<network file>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentSpec {
+    Random,
+    Heuristic(Personality),
+    Ai(PathBuf),
+    Ismcts(SearchBudget),
+    Cfr(PathBuf),
+    Neural(PathBuf),
+}
+
+impl AgentSpec {
+    /// Creates a fresh agent for one game.
+    pub fn build(&self, name: &str, seed: u64) -> Result<Box<dyn Player>, String> {
+        self.build_with_model(name, seed, None)
+    }
+
+    /// Like [`AgentSpec::build`], handing `opponents` to the agents that can use it.
+    pub fn build_with_model(&self, name: &str, seed: u64, opponents: Option<Arc<OpponentModel>>) -> Result<Box<dyn Player>, String> {
+        Ok(match (self, opponents) {
+            (AgentSpec::Heuristic(personality), Some(opponents)) => Box::new(HeuristicPlayer::new(name, *personality, seed).with_opponent_model(opponents)),
+            (AgentSpec::Ismcts(budget), Some(opponents)) => Box::new(IsmctsPlayer::new(name, *budget, seed).with_opponent_model(opponents)),
+            (AgentSpec::Random, _) => Box::new(RandomPlayer::new(name, seed)),
+            (AgentSpec::Heuristic(personality), None) => Box::new(HeuristicPlayer::new(name, *personality, seed)),
+            (AgentSpec::Ai(path), _) => Box::new(AIPlayer::load(name.to_string(), path)?),
+            (AgentSpec::Ismcts(budget), None) => Box::new(IsmctsPlayer::new(name, *budget, seed)),
+            (AgentSpec::Cfr(path), _) => Box::new(CfrPlayer::load(name, path, seed)?),
+            (AgentSpec::Neural(path), _) => Box::new(NeuralPlayer::load(name, path)?),
+        })
+    }
+
+    /// Parses a comma-separated list of specs.
+    pub fn parse_list(specs: &str) -> Result<Vec<AgentSpec>, String> {
+        specs.split(',').map(|s| s.trim().parse()).collect()
+    }
+}
+
+impl FromStr for AgentSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match spec.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (spec, None),
+        };
+        match (kind, arg) {
+            ("random", None) => Ok(AgentSpec::Random),
+            ("heuristic", None) | ("heuristic", Some("honest")) => Ok(AgentSpec::Heuristic(Personality::honest())),
+            ("heuristic", Some("aggressive")) => Ok(AgentSpec::Heuristic(Personality::aggressive_bluffer())),
+            ("heuristic", Some("cautious")) => Ok(AgentSpec::Heuristic(Personality::cautious_challenger())),
+            ("ai", Some(path)) => Ok(AgentSpec::Ai(PathBuf::from(path))),
+            ("ismcts", None) => Ok(AgentSpec::Ismcts(SearchBudget::Iterations(200))),
+            ("ismcts", Some(budget)) => {
+                let invalid = || format!("Invalid search budget '{}'", budget);
+                match budget.strip_suffix("ms") {
+                    Some(ms) => Ok(AgentSpec::Ismcts(SearchBudget::Time(Duration::from_millis(ms.parse().map_err(|_| invalid())?)))),
+                    None => Ok(AgentSpec::Ismcts(SearchBudget::Iterations(budget.parse().map_err(|_| invalid())?))),
+                }
+            },
+            ("cfr", Some(path)) => Ok(AgentSpec::Cfr(PathBuf::from(path))),
+            ("nn", Some(path)) => Ok(AgentSpec::Neural(PathBuf::from(path))),
+            _ => Err(format!("Unknown agent spec '{}'", spec)),
+        }
+    }
+}
+
+impl fmt::Display for AgentSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AgentSpec::Random => write!(f, "random"),
+            AgentSpec::Heuristic(p) if *p == Personality::aggressive_bluffer() => write!(f, "heuristic:aggressive"),
+            AgentSpec::Heuristic(p) if *p == Personality::cautious_challenger() => write!(f, "heuristic:cautious"),
+            AgentSpec::Heuristic(_) => write!(f, "heuristic"),
+            AgentSpec::Ai(path) => write!(f, "ai:{}", path.display()),
+            AgentSpec::Ismcts(SearchBudget::Iterations(n)) => write!(f, "ismcts:{}", n),
+            AgentSpec::Ismcts(SearchBudget::Time(limit)) => write!(f, "ismcts:{}ms", limit.as_millis()),
+            AgentSpec::Cfr(path) => write!(f, "cfr:{}", path.display()),
+            AgentSpec::Neural(path) => write!(f, "nn:{}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::action::Action;
+use crate::character::Character;
+use crate::game::{self, GameState};
+use crate::gameai::GameStateAI;
+use crate::player::Player;
+use crate::player::heuristic::least_valuable_card;
+
+/// A game with `players` probe-driven seats, dealt from `seed` without shuffling the seating.
+pub(crate) fn new_game(players: usize, seed: u64) -> GameState {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let seats: Vec<Box<dyn Player>> = (0..players)
+        .map(|i| Box::new(ProbeSeat { name: format!("seat{}", i), coins: 2, hand: Vec::new(), probe: Rc::clone(&probe) }) as Box<dyn Player>)
+        .collect();
+    let mut game = GameState::new(seats);
+    game.verbose = false;
+    game.rng = StdRng::seed_from_u64(seed);
+    game::deal(&mut game);
+    game
+}
+
+/// A decision the engine asked for that was not in the replay script.
+pub(crate) struct Pending {
+    pub player: usize,
+    pub observation: GameStateAI,
+    pub options: Vec<Action>,
+}
+
+pub(crate) enum Advance {
+    /// The step finished using only scripted decisions.
+    Stepped(Box<GameState>),
+    Decision(Box<Pending>),
+}
+
+/// Copies `game` and plays its next step, answering decisions from `script`. Stops at
+/// the first decision beyond the script, whose remaining step is thrown away.
+pub(crate) fn advance(game: &GameState, script: &[Action]) -> Advance {
+    let probe = Rc::new(RefCell::new(Probe { script: script.to_vec(), next: 0, pending: None }));
+    let players: Vec<Box<dyn Player>> = game.players.iter()
+        .map(|p| Box::new(ProbeSeat { name: p.name(), coins: p.coins(), hand: p.hand(), probe: Rc::clone(&probe) }) as Box<dyn Player>)
+        .collect();
+    let mut copy = GameState::new(players);
+    copy.verbose = false;
+    copy.deck = game.deck.clone();
+    copy.revealed_characters = game.revealed_characters.clone();
+    copy.history = game.history.clone();
+    copy.events = game.events.clone();
+    copy.current_player = game.current_player;
+    copy.phase = game.phase;
+    copy.turn = game.turn.clone();
+    copy.tie_break = game.tie_break;
+    copy.factions = game.factions.clone();
+    copy.treasury = game.treasury;
+    copy.rules = game.rules.clone();
+    copy.teams = game.teams.clone();
+    copy.eliminations = game.eliminations.clone();
+    copy.quiet_since = game.quiet_since;
+    copy.rng = game.rng.clone();
+
+    copy.step();
+    let pending = probe.borrow_mut().pending.take();
+    match pending {
+        Some(pending) => Advance::Decision(Box::new(pending)),
+        None => Advance::Stepped(Box::new(copy)),
+    }
+}
+
+#[derive(Default)]
+struct Probe {
+    script: Vec<Action>,
+    next: usize,
+    pending: Option<Pending>,
+}
+
+/// Seat driven by the solver through a shared [`Probe`].
+struct ProbeSeat {
+    name: String,
+    coins: usize,
+    hand: Vec<Character>,
+    probe: Rc<RefCell<Probe>>,
+}
+
+impl Player for ProbeSeat {
+    fn choose_action(&self, possible_actions: Vec<Action>, game_state: &GameStateAI) -> Action {
+        let mut probe = self.probe.borrow_mut();
+        if let Some(action) = probe.script.get(probe.next).cloned() {
+            probe.next += 1;
+            return action;
+        }
+        if probe.pending.is_none() {
+            probe.pending = Some(Pending { player: game_state.player_index, observation: game_state.clone(), options: possible_actions.clone() });
+        }
+        possible_actions[0].clone()
+    }
+
+    fn choose_card(&self, _game_state: &GameState) -> Character {
+        least_valuable_card(&self.hand)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn coins(&self) -> usize {
+        self.coins
+    }
+
+    fn add_coins(&mut self, coins: usize) {
+        self.coins += coins;
+    }
+
+    fn lose_coins(&mut self, coins: usize) -> Result<(), String> {
+        if coins <= self.coins {
+            self.coins -= coins;
+            Ok(())
+        } else {
+            Err("Not enough coins".to_string())
+        }
+    }
+
+    fn hand(&self) -> Vec<Character> {
+        self.hand.clone()
+    }
+
+    fn add_card_to_hand(&mut self, card: Character) {
+        self.hand.push(card);
+    }
+
+    fn remove_card_from_hand(&mut self, card: Character) -> Result<(), String> {
+        if let Some(index) = self.hand.iter().position(|&c| c == card) {
+            self.hand.remove(index);
+            Ok(())
+        } else {
+            Err("Card not found in hand".to_string())
+        }
+    }
+}
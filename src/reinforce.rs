@@ -0,0 +1,334 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::action_space::ActionSpace;
+use crate::game::{self, GameState};
+use crate::player::Player;
+use crate::player::heuristic::{HeuristicPlayer, Personality};
+use crate::player::neural::{masked_softmax, NeuralPlayer, PolicyNet};
+use crate::player::spec::AgentSpec;
+
+/// Settings for on-policy self-play training of a [`PolicyNet`].
+#[derive(Debug, Clone)]
+pub struct ReinforceConfig {
+    pub num_players: usize,
+    /// Hidden layers of a network trained from scratch.
+    pub hidden: Vec<usize>,
+    pub iterations: usize,
+    /// Games played by the learner, on a rotating seat, before every update.
+    pub games_per_iteration: usize,
+    pub learning_rate: f64,
+    /// Gradient steps per batch of games. With more than one, the update is PPO's
+    /// clipped surrogate; with one it is plain REINFORCE.
+    pub epochs: usize,
+    /// How far PPO lets the probability of a move drift from the one it was played with.
+    pub clip: f64,
+    /// Weight of the newest return in the running-mean baseline.
+    pub baseline_rate: f64,
+    /// Chance that an opponent seat is the heuristic bot rather than a past checkpoint.
+    pub heuristic_share: f64,
+    /// A checkpoint joins the opponent pool every this many iterations.
+    pub checkpoint_every: usize,
+    /// Oldest checkpoints leave the pool beyond this many.
+    pub pool_size: usize,
+    /// Fixed opponents the greedy policy is measured against every `eval_every` iterations.
+    pub baselines: Vec<AgentSpec>,
+    pub eval_every: usize,
+    pub eval_games: usize,
+    pub seed: u64,
+    /// Learning curve, one row per iteration.
+    pub curve: Option<PathBuf>,
+    /// Where checkpoints are written, as `checkpoint-<iteration>.nn`.
+    pub checkpoint_dir: Option<PathBuf>,
+}
+
+impl Default for ReinforceConfig {
+    fn default() -> Self {
+        ReinforceConfig {
+            num_players: 3,
+            hidden: vec![64],
+            iterations: 100,
+            games_per_iteration: 20,
+            learning_rate: 0.5,
+            epochs: 1,
+            clip: 0.2,
+            baseline_rate: 0.05,
+            heuristic_share: 0.5,
+            checkpoint_every: 10,
+            pool_size: 10,
+            baselines: vec![AgentSpec::Random, AgentSpec::Heuristic(Personality::honest())],
+            eval_every: 10,
+            eval_games: 60,
+            seed: 0,
+            curve: None,
+            checkpoint_dir: None,
+        }
+    }
+}
+
+/// What one iteration of training did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationStats {
+    pub iteration: usize,
+    pub games: usize,
+    /// Learner decisions that had more than one option.
+    pub decisions: usize,
+    pub win_rate: f64,
+    /// The baseline before this iteration's games were added to it.
+    pub baseline: f64,
+    /// Mean entropy of the policy over the decisions, a sign of how much it still explores.
+    pub entropy: f64,
+    /// Greedy win rate against each of `ReinforceConfig::baselines`, when evaluated.
+    pub evaluation: Option<Vec<f64>>,
+}
+
+/// One learner decision kept for the update.
+struct Step {
+    features: Vec<f64>,
+    legal: Vec<bool>,
+    action: usize,
+    /// Probability the move was played with.
+    probability: f64,
+    entropy: f64,
+    reward: f64,
+}
+
+/// Self-play policy-gradient trainer: the learner samples from its policy against
+/// opponents drawn from its own past checkpoints and the heuristic bot, and every
+/// iteration pushes up the probability of the moves that led to wins.
+pub struct Trainer {
+    pub config: ReinforceConfig,
+    pub policy: PolicyNet,
+    pool: Vec<PolicyNet>,
+    space: ActionSpace,
+    baseline: f64,
+    rng: StdRng,
+    games_played: u64,
+    iteration: usize,
+}
+
+impl Trainer {
+    /// Starts from `policy`, e.g. one trained by imitation, or a fresh network.
+    pub fn new(config: ReinforceConfig, policy: Option<PolicyNet>) -> Result<Trainer, String> {
+        if !(2..=6).contains(&config.num_players) {
+            return Err("Games need 2 to 6 players".to_string());
+        }
+        let policy = policy.unwrap_or_else(|| PolicyNet::new(config.num_players, &config.hidden, config.seed));
+        if policy.num_players != config.num_players {
+            return Err(format!("The network plays {}-player games, not {}", policy.num_players, config.num_players));
+        }
+        Ok(Trainer {
+            space: ActionSpace::new(config.num_players),
+            pool: Vec::new(),
+            // The mean reward of a player no better than the rest of the table.
+            baseline: 2.0 / config.num_players as f64 - 1.0,
+            rng: StdRng::seed_from_u64(config.seed),
+            games_played: 0,
+            iteration: 0,
+            policy,
+            config,
+        })
+    }
+
+    /// Runs every iteration, writing the learning curve and checkpoints as configured.
+    pub fn run(&mut self) -> Result<Vec<IterationStats>, String> {
+        if let Some(dir) = &self.config.checkpoint_dir {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let mut curve = match &self.config.curve {
+            Some(path) => {
+                let mut out = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+                let baselines: Vec<String> = self.config.baselines.iter().map(|b| format!("vs_{}", b)).collect();
+                writeln!(out, "iteration,games,decisions,win_rate,baseline,entropy,{}", baselines.join(",")).map_err(|e| e.to_string())?;
+                Some(out)
+            },
+            None => None,
+        };
+
+        let mut history = Vec::new();
+        for _ in 0..self.config.iterations {
+            let stats = self.iterate()?;
+            if let Some(out) = curve.as_mut() {
+                let evaluation: Vec<String> = match &stats.evaluation {
+                    Some(rates) => rates.iter().map(|r| format!("{:.4}", r)).collect(),
+                    None => vec![String::new(); self.config.baselines.len()],
+                };
+                writeln!(out, "{},{},{},{:.4},{:.4},{:.4},{}", stats.iteration, stats.games, stats.decisions, stats.win_rate, stats.baseline, stats.entropy, evaluation.join(","))
+                    .map_err(|e| e.to_string())?;
+                out.flush().map_err(|e| e.to_string())?;
+            }
+            history.push(stats);
+        }
+        Ok(history)
+    }
+
+    /// Plays one batch of games, updates the policy and, on schedule, checkpoints and
+    /// evaluates it.
+    pub fn iterate(&mut self) -> Result<IterationStats, String> {
+        self.iteration += 1;
+        let n = self.config.num_players;
+        let mut steps = Vec::new();
+        let mut wins = 0;
+        for g in 0..self.config.games_per_iteration {
+            let seat = g % n;
+            let (won, game_steps) = self.play_training_game(seat)?;
+            wins += won as usize;
+            steps.extend(game_steps);
+        }
+
+        let baseline = self.baseline;
+        let entropy = if steps.is_empty() { 0.0 } else { steps.iter().map(|s| s.entropy).sum::<f64>() / steps.len() as f64 };
+        self.update(&steps)?;
+        let games = self.config.games_per_iteration;
+        if games > 0 {
+            let mean_reward = (2.0 * wins as f64 - games as f64) / games as f64;
+            self.baseline += self.config.baseline_rate * (mean_reward - self.baseline);
+        }
+
+        if self.iteration.is_multiple_of(self.config.checkpoint_every) {
+            self.pool.push(self.policy.clone());
+            if self.pool.len() > self.config.pool_size.max(1) {
+                self.pool.remove(0);
+            }
+            if let Some(dir) = &self.config.checkpoint_dir {
+                self.policy.save(dir.join(format!("checkpoint-{}.nn", self.iteration)))?;
+            }
+        }
+        let evaluation = if self.iteration.is_multiple_of(self.config.eval_every) {
+            Some(self.evaluate()?)
+        } else {
+            None
+        };
+
+        Ok(IterationStats {
+            iteration: self.iteration,
+            games,
+            decisions: steps.len(),
+            win_rate: if games == 0 { 0.0 } else { wins as f64 / games as f64 },
+            baseline,
+            entropy,
+            evaluation,
+        })
+    }
+
+    /// Greedy win rate of the current policy against every baseline, which fills all
+    /// other seats, with the learner's seat rotating.
+    pub fn evaluate(&self) -> Result<Vec<f64>, String> {
+        let n = self.config.num_players;
+        let mut rates = Vec::new();
+        for (b, baseline) in self.config.baselines.iter().enumerate() {
+            let mut wins = 0;
+            for g in 0..self.config.eval_games {
+                // Evaluation seeds are the same every time, so curves compare like with like.
+                let seed = self.config.seed.wrapping_mul(1_000_003).wrapping_add((b * self.config.eval_games + g) as u64).wrapping_add(1 << 40);
+                let seat = g % n;
+                let mut players: Vec<Box<dyn Player>> = Vec::new();
+                for i in 0..n {
+                    if i == seat {
+                        players.push(Box::new(NeuralPlayer::new("learner", self.policy.clone())));
+                    } else {
+                        players.push(baseline.build(&format!("{}#{}", baseline, i), seed.wrapping_mul(31).wrapping_add(i as u64))?);
+                    }
+                }
+                let game = play(players, seed, false);
+                if game.winning_player() == Some(seat) {
+                    wins += 1;
+                }
+            }
+            rates.push(if self.config.eval_games == 0 { 0.0 } else { wins as f64 / self.config.eval_games as f64 });
+        }
+        Ok(rates)
+    }
+
+    /// One game with the sampling learner in `seat`; returns whether it won and its
+    /// decisions.
+    fn play_training_game(&mut self, seat: usize) -> Result<(bool, Vec<Step>), String> {
+        let n = self.config.num_players;
+        let seed = self.config.seed.wrapping_mul(1_000_003).wrapping_add(self.games_played);
+        self.games_played += 1;
+
+        let mut players: Vec<Box<dyn Player>> = Vec::new();
+        for i in 0..n {
+            let player_seed = seed.wrapping_mul(31).wrapping_add(i as u64);
+            if i == seat {
+                players.push(Box::new(NeuralPlayer::new("learner", self.policy.clone()).with_sampling(player_seed)));
+            } else if self.pool.is_empty() || self.rng.gen::<f64>() < self.config.heuristic_share {
+                players.push(Box::new(HeuristicPlayer::new(&format!("heuristic#{}", i), Personality::honest(), player_seed)));
+            } else {
+                let checkpoint = self.pool[self.rng.gen_range(0..self.pool.len())].clone();
+                players.push(Box::new(NeuralPlayer::new(&format!("checkpoint#{}", i), checkpoint).with_sampling(player_seed)));
+            }
+        }
+        let mut game = play(players, seed, true);
+        let won = game.winning_player() == Some(seat);
+        let reward = if won { 1.0 } else { -1.0 };
+
+        let mut steps = Vec::new();
+        for decision in game.decisions.take().unwrap_or_default() {
+            if decision.player != seat || decision.options.len() < 2 {
+                continue;
+            }
+            let features = decision.observation.features();
+            let legal = self.space.mask(&decision.options, seat);
+            let action = self.space.encode(&decision.action, seat).ok_or_else(|| format!("Action {} has no index", decision.action))?;
+            steps.push(Step { features, legal, action, probability: 0.0, entropy: 0.0, reward });
+        }
+        if !steps.is_empty() {
+            let features: Vec<Vec<f64>> = steps.iter().map(|s| s.features.clone()).collect();
+            for (step, outputs) in steps.iter_mut().zip(self.policy.outputs_batch(&features)?) {
+                let p = masked_softmax(&outputs, &step.legal);
+                step.probability = p[step.action];
+                step.entropy = -p.iter().filter(|&&p| p > 0.0).map(|p| p * p.ln()).sum::<f64>();
+            }
+        }
+        Ok((won, steps))
+    }
+
+    /// Policy-gradient steps on `steps` with advantage `reward - baseline`.
+    ///
+    /// The policy is a softmax over the output layer's pre-activations, so the gradient
+    /// of `-advantage * log p(action)` there is `advantage * (p - onehot(action))`. The
+    /// network's cross-entropy gradient at those units is `output - target`, so asking
+    /// for `target = output - advantage * (p - onehot)` takes exactly that step.
+    fn update(&mut self, steps: &[Step]) -> Result<(), String> {
+        if steps.is_empty() {
+            return Ok(());
+        }
+        let features: Vec<Vec<f64>> = steps.iter().map(|s| s.features.clone()).collect();
+        for _ in 0..self.config.epochs.max(1) {
+            let outputs = self.policy.outputs_batch(&features)?;
+            let mut targets = Vec::with_capacity(steps.len());
+            for (step, outputs) in steps.iter().zip(outputs) {
+                let p = masked_softmax(&outputs, &step.legal);
+                let advantage = step.reward - self.baseline;
+                let ratio = p[step.action] / step.probability.max(1e-12);
+                // PPO: no further push once the move's probability has moved far enough.
+                let clipped = (advantage > 0.0 && ratio > 1.0 + self.config.clip) || (advantage < 0.0 && ratio < 1.0 - self.config.clip);
+                let scale = if clipped { 0.0 } else { advantage * ratio };
+                targets.push(outputs.iter().enumerate().map(|(i, &o)| {
+                    let onehot = if i == step.action { 1.0 } else { 0.0 };
+                    o - scale * (p[i] - onehot)
+                }).collect());
+            }
+            self.policy.descend(&features, &targets, self.config.learning_rate)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays a headless game seeded with `seed`, keeping the decisions if asked.
+fn play(players: Vec<Box<dyn Player>>, seed: u64, record: bool) -> GameState {
+    let mut game = GameState::new(players);
+    game.verbose = false;
+    game.rng = StdRng::seed_from_u64(seed);
+    if record {
+        game.decisions = Some(Vec::new());
+    }
+    game::deal(&mut game);
+    game.run();
+    game
+}
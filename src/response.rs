@@ -0,0 +1,137 @@
+use rand::Rng;
+
+use crate::action::Action;
+
+/// Rule used to pick a single response when several players challenge or block at once.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TieBreak {
+    /// The first responder clockwise from the player making the claim wins.
+    #[default]
+    SeatOrder,
+    /// A uniformly random responder wins.
+    Random,
+    /// The response submitted first wins, for network play where answers arrive
+    /// concurrently. The local engine asks responders by seat number, so there it
+    /// favours the lower seats.
+    FirstReceived,
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub player: usize,
+    pub action: Action,
+    /// Position among the responses submitted to the window, from 0.
+    pub received: usize,
+}
+
+/// Collects sealed responses to a claim. Nothing submitted to a window is
+/// visible to other responders until the window is closed.
+#[derive(Debug, Default)]
+pub struct ResponseWindow {
+    responses: Vec<Response>,
+}
+
+impl ResponseWindow {
+    pub fn new() -> ResponseWindow {
+        ResponseWindow { responses: Vec::new() }
+    }
+
+    pub fn submit(&mut self, player: usize, action: Action) {
+        let received = self.responses.len();
+        self.responses.push(Response { player, action, received });
+    }
+
+    pub fn responses(&self) -> &[Response] {
+        &self.responses
+    }
+
+    pub fn passes(&self) -> impl Iterator<Item = &Response> {
+        self.responses.iter().filter(|r| r.action == Action::Pass)
+    }
+
+    /// Picks the response that takes effect, if anyone did something other than pass.
+    /// `claimant` is the seat whose claim is being answered.
+    pub fn resolve<R: Rng>(&self, claimant: usize, num_players: usize, tie_break: TieBreak, rng: &mut R) -> Option<&Response> {
+        let contenders: Vec<&Response> = self.responses.iter().filter(|r| r.action != Action::Pass).collect();
+        if contenders.is_empty() {
+            return None;
+        }
+        match tie_break {
+            TieBreak::SeatOrder => contenders.into_iter().min_by_key(|r| (r.player + num_players - claimant) % num_players),
+            TieBreak::Random => Some(contenders[rng.gen_range(0..contenders.len())]),
+            TieBreak::FirstReceived => contenders.into_iter().min_by_key(|r| r.received),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{ResponseWindow, TieBreak};
+    use crate::action::{Action, ChallengeAction};
+
+    fn challenge(player: usize) -> Action {
+        Action::Challenge(ChallengeAction::Tax, player)
+    }
+
+    #[test]
+    fn nobody_responds_when_everyone_passes() {
+        let mut window = ResponseWindow::new();
+        window.submit(1, Action::Pass);
+        window.submit(2, Action::Pass);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(window.resolve(0, 3, TieBreak::SeatOrder, &mut rng).is_none());
+        assert!(window.resolve(0, 3, TieBreak::Random, &mut rng).is_none());
+        assert!(window.resolve(0, 3, TieBreak::FirstReceived, &mut rng).is_none());
+        assert_eq!(window.passes().count(), 2);
+    }
+
+    #[test]
+    fn seat_order_starts_after_the_claimant() {
+        let mut window = ResponseWindow::new();
+        for player in [0, 1, 3] {
+            window.submit(player, challenge(2));
+        }
+        window.submit(4, Action::Pass);
+        let mut rng = StdRng::seed_from_u64(0);
+        // Clockwise from seat 2: 3, 4 (passed), 0, 1.
+        assert_eq!(window.resolve(2, 5, TieBreak::SeatOrder, &mut rng).unwrap().player, 3);
+        // Clockwise from seat 4 the same answers are ranked 0, 1, 3.
+        assert_eq!(window.resolve(4, 5, TieBreak::SeatOrder, &mut rng).unwrap().player, 0);
+    }
+
+    #[test]
+    fn first_received_follows_the_submission_order() {
+        let mut window = ResponseWindow::new();
+        window.submit(4, Action::Pass);
+        window.submit(3, challenge(0));
+        window.submit(1, challenge(0));
+        let received: Vec<usize> = window.responses().iter().map(|r| r.received).collect();
+        assert_eq!(received, vec![0, 1, 2]);
+        let mut rng = StdRng::seed_from_u64(0);
+        // Seat 1 is first clockwise from the claimant, but seat 3 answered first.
+        assert_eq!(window.resolve(0, 5, TieBreak::SeatOrder, &mut rng).unwrap().player, 1);
+        assert_eq!(window.resolve(0, 5, TieBreak::FirstReceived, &mut rng).unwrap().player, 3);
+    }
+
+    #[test]
+    fn random_tie_break_is_seeded_and_reaches_every_contender() {
+        let mut window = ResponseWindow::new();
+        window.submit(0, Action::Pass);
+        for player in [1, 2, 3] {
+            window.submit(player, challenge(0));
+        }
+        let picks = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..60).map(|_| window.resolve(0, 4, TieBreak::Random, &mut rng).unwrap().player).collect::<Vec<_>>()
+        };
+        assert_eq!(picks(7), picks(7));
+        let picked = picks(7);
+        for player in [1, 2, 3] {
+            assert!(picked.contains(&player));
+        }
+        assert!(!picked.contains(&0));
+    }
+}
@@ -0,0 +1,76 @@
+/// How a player lost their last influence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EliminationCause {
+    Coup,
+    Assassination,
+    /// They challenged a claim that turned out to be true.
+    FailedChallenge,
+    /// They were caught bluffing.
+    FailedBluff,
+}
+
+impl std::fmt::Display for EliminationCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EliminationCause::Coup => write!(f, "Coup"),
+            EliminationCause::Assassination => write!(f, "Assassination"),
+            EliminationCause::FailedChallenge => write!(f, "failed challenge"),
+            EliminationCause::FailedBluff => write!(f, "failed bluff"),
+        }
+    }
+}
+
+/// Why the game stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ending {
+    /// One player, or one team, was left.
+    LastStanding,
+    /// `GameConfig::max_turns` was reached.
+    TurnLimit,
+    /// Nobody lost influence for `GameConfig::stalemate_rounds` rounds.
+    Stalemate,
+}
+
+/// A player knocked out of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elimination {
+    pub player: usize,
+    /// Turn it happened on, counting from 1.
+    pub turn: usize,
+    /// The player who made them lose the influence: the one who Couped, Assassinated,
+    /// made the proven claim or called the bluff.
+    pub by: usize,
+    pub cause: EliminationCause,
+}
+
+/// How a finished game turned out. Seats are those of `GameState::players`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    pub ending: Ending,
+    /// The last player standing, the tiebreak winner of a stopped game, or in team
+    /// games the first surviving member of the winning team. None for a draw.
+    pub winner: Option<usize>,
+    pub winning_team: Option<usize>,
+    /// Every seat from first place to last: the survivors, best tiebreak first, then
+    /// the others from the last one knocked out to the first.
+    pub placements: Vec<usize>,
+    /// In the order they happened.
+    pub eliminations: Vec<Elimination>,
+    /// Turns played, the last one included.
+    pub turns: usize,
+}
+
+impl GameResult {
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none()
+    }
+
+    /// Place of `player`, 0 for the winner.
+    pub fn placement(&self, player: usize) -> usize {
+        self.placements.iter().position(|&p| p == player).expect("every seat is placed")
+    }
+
+    pub fn elimination(&self, player: usize) -> Option<&Elimination> {
+        self.eliminations.iter().find(|e| e.player == player)
+    }
+}
@@ -0,0 +1,451 @@
+use std::fs;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::action::{Action, BlockAction, ChallengeAction};
+use crate::character::Character;
+use crate::config::GameConfig;
+use crate::pack::CharacterPack;
+
+/// A kind of turn action, whatever its target and amount.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ActionKind {
+    Income,
+    ForeignAid,
+    Tax,
+    Coup,
+    Assassinate,
+    Steal,
+    Exchange,
+    Examine,
+    Redistribute,
+    Speculate,
+    Report,
+}
+
+impl ActionKind {
+    pub fn all() -> Vec<ActionKind> {
+        vec![
+            ActionKind::Income, ActionKind::ForeignAid, ActionKind::Tax, ActionKind::Coup,
+            ActionKind::Assassinate, ActionKind::Steal, ActionKind::Exchange, ActionKind::Examine,
+            ActionKind::Redistribute, ActionKind::Speculate, ActionKind::Report,
+        ]
+    }
+
+    /// The kind of a turn action. Reformation actions come with factions rather than
+    /// with a ruleset, so they have none.
+    pub fn of(action: &Action) -> Option<ActionKind> {
+        match action {
+            Action::Income => Some(ActionKind::Income),
+            Action::ForeignAid => Some(ActionKind::ForeignAid),
+            Action::Tax => Some(ActionKind::Tax),
+            Action::Coup(_) => Some(ActionKind::Coup),
+            Action::Assassinate(_) => Some(ActionKind::Assassinate),
+            Action::Steal(_, _) => Some(ActionKind::Steal),
+            Action::Exchange => Some(ActionKind::Exchange),
+            Action::Examine(_) => Some(ActionKind::Examine),
+            Action::Redistribute => Some(ActionKind::Redistribute),
+            Action::Speculate => Some(ActionKind::Speculate),
+            Action::Report => Some(ActionKind::Report),
+            _ => None,
+        }
+    }
+
+    /// The kind of action `block` stops.
+    pub fn of_block(block: &BlockAction) -> ActionKind {
+        match block {
+            BlockAction::ForeignAid(_) => ActionKind::ForeignAid,
+            BlockAction::Assassinate(_) => ActionKind::Assassinate,
+            BlockAction::Stealing(_, _) => ActionKind::Steal,
+        }
+    }
+
+    /// Whether the engine can block this kind of action.
+    pub fn is_blockable(&self) -> bool {
+        matches!(self, ActionKind::ForeignAid | ActionKind::Assassinate | ActionKind::Steal)
+    }
+
+    /// Whether the engine lets players challenge a claim to this kind of action.
+    pub fn is_challengeable(&self) -> bool {
+        !matches!(self, ActionKind::Income | ActionKind::ForeignAid | ActionKind::Coup)
+    }
+}
+
+/// One character of a ruleset: how many copies are in the deck, which actions claiming
+/// it allows and which actions claiming it blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharacterRules {
+    pub character: Character,
+    pub copies: usize,
+    #[serde(default)]
+    pub actions: Vec<ActionKind>,
+    #[serde(default)]
+    pub blocks: Vec<ActionKind>,
+}
+
+/// Which characters are in play and what they do, plus the [`GameConfig`] house rules,
+/// whose fields sit at the top level of the file. The defaults are the base game.
+/// Loaded from TOML, e.g.
+///
+/// ```toml
+/// name = "No Foreign Aid"
+/// general_actions = ["Income", "Coup"]
+/// tax = 4
+///
+/// [[characters]]
+/// character = "Duke"
+/// copies = 3
+/// actions = ["Tax"]
+/// ```
+///
+/// or the same fields in JSON. Missing fields keep their base game value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ruleset {
+    pub name: String,
+    /// At most one character per `Character::slot`.
+    pub characters: Vec<CharacterRules>,
+    /// Actions anyone may take without claiming a character.
+    pub general_actions: Vec<ActionKind>,
+    /// Cards drawn, then returned, by an Exchange.
+    pub exchange_draw: usize,
+    #[serde(flatten)]
+    pub config: GameConfig,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset::classic()
+    }
+}
+
+impl Ruleset {
+    /// The base game.
+    pub fn classic() -> Ruleset {
+        let character = |character, actions: &[ActionKind], blocks: &[ActionKind]| CharacterRules {
+            character,
+            copies: 3,
+            actions: actions.to_vec(),
+            blocks: blocks.to_vec(),
+        };
+        Ruleset {
+            name: "Classic".to_string(),
+            characters: vec![
+                character(Character::Duke, &[ActionKind::Tax], &[ActionKind::ForeignAid]),
+                character(Character::Assassin, &[ActionKind::Assassinate], &[]),
+                character(Character::Captain, &[ActionKind::Steal], &[ActionKind::Steal]),
+                character(Character::Ambassador, &[ActionKind::Exchange], &[ActionKind::Steal]),
+                character(Character::Contessa, &[], &[ActionKind::Assassinate]),
+            ],
+            general_actions: vec![ActionKind::Income, ActionKind::ForeignAid, ActionKind::Coup],
+            exchange_draw: 2,
+            config: GameConfig::default(),
+        }
+    }
+
+    /// These rules, stopping a game once nobody has lost influence for `rounds` rounds.
+    pub fn with_stalemate_rounds(mut self, rounds: usize) -> Ruleset {
+        self.config.stalemate_rounds = Some(rounds);
+        self
+    }
+
+    /// These rules played with the house rules in `config`.
+    pub fn with_config(mut self, config: GameConfig) -> Ruleset {
+        self.config = config;
+        self
+    }
+
+    /// The base game with the Inquisitor in place of the Ambassador.
+    pub fn inquisitor() -> Ruleset {
+        let mut rules = Ruleset::classic();
+        rules.name = "Inquisitor".to_string();
+        rules.characters[3] = CharacterRules {
+            character: Character::Inquisitor,
+            copies: 3,
+            actions: vec![ActionKind::Exchange, ActionKind::Examine],
+            blocks: vec![ActionKind::Steal],
+        };
+        rules.exchange_draw = 1;
+        rules
+    }
+
+    /// The deck selection step: these rules with each of `roles`, taken from the
+    /// [`CharacterPack`]s, in place of the character sharing its slot.
+    pub fn with_roles(mut self, roles: &[Character]) -> Result<Ruleset, String> {
+        for &character in roles {
+            let role = CharacterPack::role(character).ok_or_else(|| format!("No pack has the {}", character))?;
+            match self.characters.iter().position(|c| c.character.slot() == character.slot()) {
+                Some(i) => self.characters[i] = CharacterRules { copies: self.characters[i].copies, ..role },
+                None => self.characters.push(role),
+            }
+        }
+        if !roles.is_empty() {
+            let names: Vec<String> = roles.iter().map(|c| c.to_string()).collect();
+            self.name = format!("{} with {}", self.name, names.join(", "));
+        }
+        Ok(self)
+    }
+
+    /// These rules with every slot filled by a role drawn from all the packs.
+    pub fn with_random_roles<R: Rng>(self, rng: &mut R) -> Ruleset {
+        let roles: Vec<Character> = Character::all().iter()
+            .filter_map(|c| CharacterPack::candidates(c.slot()).choose(rng).map(|r| r.character))
+            .collect();
+        self.with_roles(&roles).expect("every candidate comes from a pack")
+    }
+
+    /// Reads a ruleset from a `.json` file, or from TOML for any other extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Ruleset, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        if path.extension().is_some_and(|e| e == "json") {
+            Ruleset::from_json(&text)
+        } else {
+            Ruleset::from_toml(&text)
+        }
+        .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Ruleset, String> {
+        let rules: Ruleset = toml::from_str(text).map_err(|e| e.to_string())?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn from_json(text: &str) -> Result<Ruleset, String> {
+        let rules: Ruleset = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Writes the ruleset as JSON for a `.json` path, as TOML otherwise.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let text = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(self).map_err(|e| e.to_string())?
+        } else {
+            toml::to_string(self).map_err(|e| e.to_string())?
+        };
+        fs::write(path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+
+    /// Checks that the ruleset describes a playable game.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.characters.iter().all(|c| c.copies == 0) {
+            return Err("The deck has no cards".to_string());
+        }
+        for (i, rules) in self.characters.iter().enumerate() {
+            if self.characters[..i].iter().any(|c| c.character.slot() == rules.character.slot()) {
+                return Err(format!("{} shares its slot with another character", rules.character));
+            }
+            if let Some(kind) = rules.blocks.iter().find(|k| !k.is_blockable()) {
+                return Err(format!("{} cannot block {:?}", rules.character, kind));
+            }
+            if let Some(kind) = rules.actions.iter().find(|k| !k.is_challengeable() && !self.general_actions.contains(k)) {
+                return Err(format!("{:?} cannot be claimed by {}, claims to it cannot be challenged", kind, rules.character));
+            }
+        }
+        if !self.general_actions.contains(&ActionKind::Income) || !self.general_actions.contains(&ActionKind::Coup) {
+            return Err("Income and Coup must be general actions".to_string());
+        }
+        if self.exchange_draw == 0 {
+            return Err("An Exchange must draw at least one card".to_string());
+        }
+        self.config.validate()
+    }
+
+    /// The characters in play, in the order given.
+    pub fn characters(&self) -> Vec<Character> {
+        self.characters.iter().map(|c| c.character).collect()
+    }
+
+    /// Checks that the deck can deal every hand of a `num_players` game and still
+    /// serve an Exchange.
+    pub fn check_players(&self, num_players: usize) -> Result<(), String> {
+        let needed = num_players * self.config.influence + self.exchange_draw;
+        let cards = self.deck().len();
+        if needed > cards {
+            return Err(format!("{} players with {} influence need {} cards, the deck has {}", num_players, self.config.influence, needed, cards));
+        }
+        Ok(())
+    }
+
+    /// The full deck, one of each character per round like `Character::deck`.
+    pub fn deck(&self) -> Vec<Character> {
+        let rounds = self.characters.iter().map(|c| c.copies).max().unwrap_or(0);
+        let mut deck = Vec::new();
+        for round in 0..rounds {
+            deck.extend(self.characters.iter().filter(|c| c.copies > round).map(|c| c.character));
+        }
+        deck
+    }
+
+    /// Whether anyone may take `kind`, openly or by claiming a character.
+    pub fn enables(&self, kind: ActionKind) -> bool {
+        self.general_actions.contains(&kind) || !self.claimants(kind).is_empty()
+    }
+
+    /// Characters that allow `kind`. Empty for general actions, which need no claim.
+    pub fn claimants(&self, kind: ActionKind) -> Vec<Character> {
+        if self.general_actions.contains(&kind) {
+            return Vec::new();
+        }
+        self.characters.iter().filter(|c| c.actions.contains(&kind)).map(|c| c.character).collect()
+    }
+
+    /// Characters that block `kind`.
+    pub fn blockers(&self, kind: ActionKind) -> Vec<Character> {
+        self.characters.iter().filter(|c| c.blocks.contains(&kind)).map(|c| c.character).collect()
+    }
+
+    /// Characters that back the claim behind `action`: the claimants of a turn action or
+    /// the character a block names. Empty for moves that claim nothing, and for Embezzle,
+    /// which claims to hold none of the characters that collect Tax.
+    pub fn claimed(&self, action: &Action) -> Vec<Character> {
+        match action {
+            Action::Block(_, _, character) => vec![*character],
+            _ => ActionKind::of(action).map(|kind| self.claimants(kind)).unwrap_or_default(),
+        }
+    }
+
+    /// Characters that back the claim disputed by `challenge`.
+    pub fn challenged(&self, challenge: &ChallengeAction) -> Vec<Character> {
+        match challenge {
+            ChallengeAction::BlockAssassination(_) => self.blockers(ActionKind::Assassinate),
+            ChallengeAction::BlockStealing(_, _) => self.blockers(ActionKind::Steal),
+            ChallengeAction::BlockForeignAid(_) => self.blockers(ActionKind::ForeignAid),
+            ChallengeAction::Exchange => self.claimants(ActionKind::Exchange),
+            ChallengeAction::Stealing(_, _) => self.claimants(ActionKind::Steal),
+            ChallengeAction::Assassination(_) => self.claimants(ActionKind::Assassinate),
+            ChallengeAction::Tax => self.claimants(ActionKind::Tax),
+            ChallengeAction::Embezzle => Vec::new(),
+            ChallengeAction::Examine(_) => self.claimants(ActionKind::Examine),
+            ChallengeAction::Redistribute => self.claimants(ActionKind::Redistribute),
+            ChallengeAction::Speculate => self.claimants(ActionKind::Speculate),
+            ChallengeAction::Report => self.claimants(ActionKind::Report),
+        }
+    }
+
+    /// Whether taking `action` claims a character, so that it can be challenged.
+    pub fn is_claim(&self, action: &Action) -> bool {
+        match action {
+            Action::Block(_, _, _) | Action::Embezzle => true,
+            _ => ActionKind::of(action).is_some_and(|kind| !self.claimants(kind).is_empty()),
+        }
+    }
+
+    /// Whether `hand` backs the claim behind `action`. Embezzle claims to hold none of the
+    /// characters that collect Tax.
+    pub fn proves(&self, hand: &[Character], action: &Action) -> bool {
+        let holds_any = |characters: Vec<Character>| characters.iter().any(|c| hand.contains(c));
+        match action {
+            Action::Embezzle => !holds_any(self.claimants(ActionKind::Tax)),
+            Action::Block(_, _, character) => hand.contains(character),
+            _ => {
+                let claimed = self.claimed(action);
+                claimed.is_empty() || holds_any(claimed)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActionKind, Ruleset};
+    use crate::action::{Action, BlockAction, ChallengeAction};
+    use crate::character::Character;
+
+    #[test]
+    fn classic_deck_matches_the_base_game() {
+        assert_eq!(Ruleset::classic().deck(), Character::deck(&Character::all()));
+        assert_eq!(Ruleset::inquisitor().deck(), Character::deck(&Character::inquisition()));
+    }
+
+    #[test]
+    fn rulesets_round_trip_through_toml_and_json() {
+        for rules in [Ruleset::classic(), Ruleset::inquisitor()] {
+            let toml = toml::to_string(&rules).unwrap();
+            assert_eq!(Ruleset::from_toml(&toml).unwrap(), rules);
+            let json = serde_json::to_string(&rules).unwrap();
+            assert_eq!(Ruleset::from_json(&json).unwrap(), rules);
+        }
+    }
+
+    #[test]
+    fn missing_fields_keep_the_base_game() {
+        let rules = Ruleset::from_toml("tax = 4\nmax_turns = 50\ngeneral_actions = [\"Income\", \"Coup\"]").unwrap();
+        assert_eq!(rules.config.tax, 4);
+        assert_eq!(rules.config.max_turns, Some(50));
+        assert_eq!(rules.config.stalemate_rounds, None);
+        assert_eq!(rules.characters, Ruleset::classic().characters);
+        assert!(!rules.enables(ActionKind::ForeignAid));
+        assert!(rules.enables(ActionKind::Tax));
+    }
+
+    #[test]
+    fn claims_follow_the_character_powers() {
+        let rules = Ruleset::classic();
+        assert!(rules.proves(&[Character::Duke], &Action::Tax));
+        assert!(!rules.proves(&[Character::Captain], &Action::Tax));
+        assert!(rules.proves(&[Character::Ambassador], &Action::Block(BlockAction::Stealing(1, 2), 0, Character::Ambassador)));
+        // Only the character named in the block counts, not any other blocker held.
+        assert!(!rules.proves(&[Character::Ambassador], &Action::Block(BlockAction::Stealing(1, 2), 0, Character::Captain)));
+        assert!(!rules.proves(&[Character::Duke], &Action::Embezzle));
+        assert!(!rules.is_claim(&Action::Income));
+        assert!(rules.is_claim(&Action::Steal(1, 2)));
+
+        let inquisitor = Ruleset::inquisitor();
+        assert!(inquisitor.proves(&[Character::Inquisitor], &Action::Examine(1)));
+        assert!(!inquisitor.proves(&[Character::Ambassador], &Action::Exchange));
+    }
+
+    #[test]
+    fn moved_powers_change_what_a_claim_names() {
+        let mut rules = Ruleset::classic();
+        rules.characters[0].actions.clear();
+        rules.characters[2].actions.push(ActionKind::Tax);
+        rules.characters[2].blocks.push(ActionKind::ForeignAid);
+        rules.validate().unwrap();
+        assert_eq!(rules.claimed(&Action::Tax), vec![Character::Captain]);
+        assert_eq!(rules.challenged(&ChallengeAction::Tax), vec![Character::Captain]);
+        assert_eq!(rules.blockers(ActionKind::ForeignAid), vec![Character::Duke, Character::Captain]);
+        assert_eq!(rules.claimed(&Action::Block(BlockAction::ForeignAid(0), 0, Character::Captain)), vec![Character::Captain]);
+        assert_eq!(rules.challenged(&ChallengeAction::BlockStealing(1, 2)), vec![Character::Captain, Character::Ambassador]);
+        assert!(rules.claimed(&Action::Income).is_empty());
+        // With no Duke power left, Embezzle claims to hold no Captain.
+        assert!(rules.proves(&[Character::Duke], &Action::Embezzle));
+        assert!(!rules.proves(&[Character::Captain], &Action::Embezzle));
+
+        rules.general_actions.retain(|&k| k != ActionKind::ForeignAid);
+        rules.characters[0].actions.push(ActionKind::ForeignAid);
+        assert!(rules.validate().is_err());
+    }
+
+    #[test]
+    fn selected_roles_take_the_slot_they_replace() {
+        let rules = Ruleset::classic().with_roles(&[Character::Socialist, Character::Reporter]).unwrap();
+        assert_eq!(rules.characters(), vec![Character::Socialist, Character::Assassin, Character::Captain, Character::Reporter, Character::Contessa]);
+        assert_eq!(rules.deck().len(), 15);
+        assert!(!rules.enables(ActionKind::Tax));
+        assert!(rules.proves(&[Character::Socialist], &Action::Redistribute));
+        assert!(rules.proves(&[Character::Reporter], &Action::Block(BlockAction::Stealing(1, 2), 0, Character::Reporter)));
+        assert_eq!(rules.blockers(ActionKind::ForeignAid), vec![Character::Socialist]);
+        rules.validate().unwrap();
+    }
+
+    #[test]
+    fn invalid_rulesets_are_rejected() {
+        assert!(Ruleset::from_toml("exchange_draw = 0").is_err());
+        assert!(Ruleset::from_toml("coup_cost = 12").is_err());
+        assert!(Ruleset::from_toml("influence = 0").is_err());
+        assert!(Ruleset::from_toml("stalemate_rounds = 0").is_err());
+        assert!(Ruleset::from_toml("general_actions = [\"Income\"]").is_err());
+        let blocks_tax = "[[characters]]\ncharacter = \"Duke\"\ncopies = 3\nblocks = [\"Tax\"]";
+        assert!(Ruleset::from_toml(blocks_tax).is_err());
+        let same_slot = "[[characters]]\ncharacter = \"Ambassador\"\ncopies = 3\n[[characters]]\ncharacter = \"Inquisitor\"\ncopies = 3";
+        assert!(Ruleset::from_toml(same_slot).is_err());
+    }
+}
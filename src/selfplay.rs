@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::action_space::ActionSpace;
+use crate::config::STALEMATE_ROUNDS;
+use crate::dataset::{Dataset, Sample};
+use crate::game::{self, GameState};
+use crate::opponent::OpponentModel;
+use crate::player::Player;
+use crate::player::spec::AgentSpec;
+use crate::ruleset::Ruleset;
+use crate::simulation;
+
+/// Settings for a batch of headless self-play games.
+#[derive(Debug, Clone)]
+pub struct SelfPlayConfig {
+    pub games: usize,
+    /// One agent per seat.
+    pub agents: Vec<AgentSpec>,
+    pub threads: usize,
+    pub seed: u64,
+    /// Rules every game is played under. The default stops games after
+    /// [`STALEMATE_ROUNDS`] rounds without a lost influence.
+    pub rules: Ruleset,
+    /// Statistics about the agents handed to those that exploit them.
+    pub opponent_model: Option<Arc<OpponentModel>>,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        SelfPlayConfig {
+            games: 100,
+            agents: vec![AgentSpec::Random; 3],
+            threads: 1,
+            seed: 0,
+            rules: Ruleset::classic().with_stalemate_rounds(STALEMATE_ROUNDS),
+            opponent_model: None,
+        }
+    }
+}
+
+/// Plays one game under `rules` and turns every decision into a sample labelled with its
+/// [`ActionSpace`] index, which for 3 players is the way `AIPlayer` reads it. The
+/// outcome is 1 for decisions taken by the winner, or the winning team, and -1 otherwise.
+pub fn play_game(agents: &[AgentSpec], rules: &Ruleset, seed: u64) -> Result<Dataset, String> {
+    play_game_with_model(agents, rules, seed, None).map(|(dataset, _)| dataset)
+}
+
+/// Like [`play_game`], giving the agents `opponents` and also returning the statistics
+/// this game adds to an opponent model.
+pub fn play_game_with_model(agents: &[AgentSpec], rules: &Ruleset, seed: u64, opponents: Option<Arc<OpponentModel>>) -> Result<(Dataset, OpponentModel), String> {
+    let mut players: Vec<Box<dyn Player>> = Vec::new();
+    for (i, agent) in agents.iter().enumerate() {
+        players.push(agent.build_with_model(&format!("{}#{}", agent, i), seed.wrapping_mul(31).wrapping_add(i as u64), opponents.clone())?);
+    }
+
+    let mut game = GameState::new(players);
+    game.verbose = false;
+    game.rng = StdRng::seed_from_u64(seed);
+    game.set_rules(rules.clone());
+    game.decisions = Some(Vec::new());
+    game::start_game(&mut game);
+    game.run();
+
+    let mut dataset = Dataset::new();
+    let space = ActionSpace::for_game(&game);
+    for decision in game.decisions.take().unwrap_or_default() {
+        let label = match space.encode(&decision.action, decision.player) {
+            Some(label) => label,
+            None => return Err(format!("Action {} has no label", decision.action)),
+        };
+        let won = game.has_won(decision.player);
+        dataset.push_sample(Sample {
+            features: decision.observation.features(),
+            legal: space.mask(&decision.options, decision.player),
+            label,
+            outcome: if won { 1.0 } else { -1.0 },
+        });
+    }
+
+    let names: Vec<String> = game.players.iter().map(|p| p.name()).collect();
+    let mut stats = OpponentModel::new();
+    stats.record_game(&names, &game.events, game.winning_player(), &game.rules);
+    Ok((dataset, stats))
+}
+
+/// Runs `config.games` games spread over `config.threads` worker threads with
+/// [`simulation::run_games`]. Samples are returned in game order regardless of which
+/// thread played them, together with the opponent statistics gathered from the games.
+pub fn generate(config: &SelfPlayConfig) -> Result<(Dataset, OpponentModel), String> {
+    if !(2..=6).contains(&config.agents.len()) {
+        return Err("Games need 2 to 6 players".to_string());
+    }
+    config.rules.check_players(config.agents.len())?;
+    let results = simulation::run_games(config.games, config.threads, config.seed, |_, seed| {
+        play_game_with_model(&config.agents, &config.rules, seed, config.opponent_model.clone())
+    })?;
+
+    let mut dataset = Dataset::new();
+    let mut stats = OpponentModel::new();
+    for (games, game_stats) in results {
+        dataset.extend(games);
+        stats.merge(&game_stats);
+    }
+    Ok((dataset, stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, SelfPlayConfig};
+    use crate::player::heuristic::Personality;
+    use crate::player::spec::AgentSpec;
+
+    #[test]
+    fn seeded_runs_give_the_same_samples() {
+        let config = SelfPlayConfig {
+            games: 6,
+            agents: vec![AgentSpec::Random, AgentSpec::Heuristic(Personality::honest()), AgentSpec::Random],
+            seed: 11,
+            ..SelfPlayConfig::default()
+        };
+        let (dataset, stats) = generate(&config).unwrap();
+        assert!(!dataset.is_empty());
+        let (again, again_stats) = generate(&SelfPlayConfig { threads: 3, ..config }).unwrap();
+        assert_eq!((again, again_stats), (dataset, stats));
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt::Write;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::config::STALEMATE_ROUNDS;
+use crate::event::Event;
+use crate::game::{self, GameState};
+use crate::player::Player;
+use crate::player::spec::AgentSpec;
+use crate::ruleset::Ruleset;
+use crate::setup::{SeatOrder, Setup};
+
+/// Points a game is worth. A player scores `outlasted` for every opponent knocked out
+/// before them, and the winner also scores `win`. Survivors tied on the tiebreak of a
+/// stopped game score the same.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scoring {
+    pub win: usize,
+    pub outlasted: usize,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring { win: 3, outlasted: 1 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    /// Best of this many games.
+    pub games: usize,
+    pub seed: u64,
+    pub scoring: Scoring,
+    pub rules: Ruleset,
+    /// Play the Reformation variant with factions and the Treasury Reserve.
+    pub reformation: bool,
+    /// Setup of every game. The default keeps the seats fixed, so that the rotation of
+    /// the table decides who moves first.
+    pub setup: Setup,
+    /// Keep playing after a player has won more games than anyone else still could.
+    pub play_out: bool,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            games: 5,
+            seed: 0,
+            scoring: Scoring::default(),
+            rules: Ruleset::classic().with_stalemate_rounds(STALEMATE_ROUNDS),
+            reformation: false,
+            setup: Setup::default().with_seating(SeatOrder::Fixed),
+            play_out: false,
+        }
+    }
+}
+
+/// Cumulative results of one player over a match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchStanding {
+    pub agent: AgentSpec,
+    pub name: String,
+    pub points: usize,
+    pub wins: usize,
+    pub games: usize,
+    /// How often the player finished in each place, first place first.
+    pub finishes: Vec<usize>,
+}
+
+/// A series of games between the same players. The table keeps its order and the seat
+/// that moves first rotates by one every game, before `MatchConfig::setup` is applied.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub config: MatchConfig,
+    pub standings: Vec<MatchStanding>,
+    /// Finishing order of every game played, as indices into `standings`, winner first.
+    pub results: Vec<Vec<usize>>,
+}
+
+impl Match {
+    /// A match between `agents`, named `<spec>#<index>` like in simulations.
+    pub fn new(agents: Vec<AgentSpec>, config: MatchConfig) -> Result<Match, String> {
+        if config.games == 0 {
+            return Err("A match needs at least one game".to_string());
+        }
+        config.rules.check_players(agents.len())?;
+        let n = agents.len();
+        let standings = agents.into_iter().enumerate()
+            .map(|(i, agent)| MatchStanding { name: format!("{}#{}", agent, i), agent, points: 0, wins: 0, games: 0, finishes: vec![0; n] })
+            .collect();
+        Ok(Match { config, standings, results: Vec::new() })
+    }
+
+    pub fn with_names(mut self, names: &[String]) -> Self {
+        for (standing, name) in self.standings.iter_mut().zip(names) {
+            standing.name = name.clone();
+        }
+        self
+    }
+
+    /// Whether every game was played or, unless playing out, the leader can no longer
+    /// be caught in wins.
+    pub fn is_over(&self) -> bool {
+        let played = self.results.len();
+        if played >= self.config.games {
+            return true;
+        }
+        if self.config.play_out {
+            return false;
+        }
+        let left = self.config.games - played;
+        let leader = self.leader();
+        self.standings.iter().enumerate().all(|(i, s)| i == leader || s.wins + left < self.standings[leader].wins)
+    }
+
+    /// Plays the next game and scores it. Returns its finishing order, winner first.
+    /// Nobody scores the win points of a drawn game.
+    pub fn play_game(&mut self) -> Result<Vec<usize>, String> {
+        let g = self.results.len();
+        let n = self.standings.len();
+        // The first game uses the match seed itself, so a one-game match is a plain game.
+        let seed = self.config.seed.wrapping_add((g as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let seating: Vec<usize> = (0..n).map(|seat| (seat + g) % n).collect();
+
+        let mut players: Vec<Box<dyn Player>> = Vec::new();
+        for (seat, &entrant) in seating.iter().enumerate() {
+            let standing = &self.standings[entrant];
+            players.push(standing.agent.build(&standing.name, seed.wrapping_mul(31).wrapping_add(seat as u64))?);
+        }
+        let mut game = GameState::new(players);
+        game.verbose = false;
+        game.rng = StdRng::seed_from_u64(seed);
+        game.set_rules(self.config.rules.clone());
+        game::start_game_with(&mut game, &self.config.setup)?;
+        if self.config.reformation {
+            game.enable_reformation();
+        }
+        game.run();
+
+        let seated = game.events.iter().find_map(|e| match e {
+            Event::Seated { order } => Some(order.clone()),
+            _ => None,
+        }).expect("setup seats the players");
+        let result = game.result().expect("the game ran to the end");
+        let order: Vec<usize> = result.placements.iter().map(|&seat| seating[seated[seat]]).collect();
+        let alive = game.alive_players();
+        let tiebreak = |seat: usize| (game.players[seat].hand().len(), game.players[seat].coins());
+        let scoring = self.config.scoring;
+        for (place, (&seat, &entrant)) in result.placements.iter().zip(order.iter()).enumerate() {
+            //survivors level on the tiebreak share the best of their places
+            let place = if alive.contains(&seat) {
+                result.placements.iter().position(|&other| alive.contains(&other) && tiebreak(other) == tiebreak(seat)).unwrap_or(place)
+            } else {
+                place
+            };
+            let standing = &mut self.standings[entrant];
+            standing.games += 1;
+            standing.finishes[place] += 1;
+            standing.points += (n - 1 - place) * scoring.outlasted;
+            if place == 0 && !result.is_draw() {
+                standing.wins += 1;
+                standing.points += scoring.win;
+            }
+        }
+        self.results.push(order.clone());
+        Ok(order)
+    }
+
+    /// Plays games until the match is over.
+    pub fn play(&mut self) -> Result<(), String> {
+        while !self.is_over() {
+            self.play_game()?;
+        }
+        Ok(())
+    }
+
+    /// The player ahead on wins, then points, then the earlier seat.
+    pub fn leader(&self) -> usize {
+        (0..self.standings.len())
+            .max_by_key(|&i| (self.standings[i].wins, self.standings[i].points, std::cmp::Reverse(i)))
+            .unwrap_or(0)
+    }
+
+    /// The player ahead on wins, then points, or None if several share the lead, e.g.
+    /// after a single drawn game.
+    pub fn winner(&self) -> Option<usize> {
+        let best = self.standings.iter().map(|s| (s.wins, s.points)).max()?;
+        let leaders: Vec<usize> = (0..self.standings.len()).filter(|&i| (self.standings[i].wins, self.standings[i].points) == best).collect();
+        if leaders.len() == 1 { Some(leaders[0]) } else { None }
+    }
+
+    /// The standings, best first.
+    pub fn table(&self) -> Vec<MatchStanding> {
+        let mut table = self.standings.clone();
+        table.sort_by_key(|s| std::cmp::Reverse((s.wins, s.points)));
+        table
+    }
+}
+
+/// Standings as a plain-text table.
+pub fn report(table: &[MatchStanding]) -> String {
+    let width = table.iter().map(|s| s.name.len()).max().unwrap_or(5).max(5);
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>4}  {:<width$}  {:>6}  {:>6}  {:>6}  finishes", "rank", "agent", "games", "wins", "points", width = width);
+    for (rank, s) in table.iter().enumerate() {
+        let finishes: Vec<String> = s.finishes.iter().map(|f| f.to_string()).collect();
+        let _ = writeln!(out, "{:>4}  {:<width$}  {:>6}  {:>6}  {:>6}  {}", rank + 1, s.name, s.games, s.wins, s.points, finishes.join("/"), width = width);
+    }
+    out
+}
@@ -0,0 +1,55 @@
+/// How the players take their seats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeatOrder {
+    /// In the order they were passed to `GameState::new`.
+    Fixed,
+    Random,
+}
+
+/// Who takes the first turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstPlayer {
+    Seat(usize),
+    Random,
+}
+
+/// Options of the pre-game setup run by `game::start_game_with`. The default shuffles
+/// the seats and lets seat 0 start, like `game::start_game`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setup {
+    pub seating: SeatOrder,
+    pub first_player: FirstPlayer,
+    /// The official two-player rule: the first player starts with 1 coin.
+    pub two_player_rule: bool,
+    /// Every player draws one card more than their influence and returns one, e.g.
+    /// draw 3 and keep 2.
+    pub draft: bool,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Setup { seating: SeatOrder::Random, first_player: FirstPlayer::Seat(0), two_player_rule: false, draft: false }
+    }
+}
+
+impl Setup {
+    pub fn with_seating(mut self, seating: SeatOrder) -> Self {
+        self.seating = seating;
+        self
+    }
+
+    pub fn with_first_player(mut self, first_player: FirstPlayer) -> Self {
+        self.first_player = first_player;
+        self
+    }
+
+    pub fn with_two_player_rule(mut self) -> Self {
+        self.two_player_rule = true;
+        self
+    }
+
+    pub fn with_draft(mut self) -> Self {
+        self.draft = true;
+        self
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::action::Action;
+use crate::config::STALEMATE_ROUNDS;
+use crate::event::Event;
+use crate::game::{self, GameState};
+use crate::player::Player;
+use crate::player::spec::AgentSpec;
+use crate::result::GameResult;
+use crate::ruleset::Ruleset;
+use crate::setup::Setup;
+use crate::team::Teams;
+
+/// Settings for a batch of independent headless games.
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// One agent per seat; seating follows `setup`.
+    pub agents: Vec<AgentSpec>,
+    pub games: usize,
+    pub threads: usize,
+    pub seed: u64,
+    /// Play the Reformation variant with factions and the Treasury Reserve.
+    pub reformation: bool,
+    /// Characters in play, their powers and the coin amounts of the actions.
+    pub rules: Ruleset,
+    /// Team play, with `team[i]` the team of `agents[i]`.
+    pub teams: Option<Teams>,
+    /// Seating, first player and dealing options.
+    pub setup: Setup,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            agents: vec![AgentSpec::Random; 3],
+            games: 1000,
+            threads: 1,
+            seed: 0,
+            reformation: false,
+            rules: Ruleset::classic().with_stalemate_rounds(STALEMATE_ROUNDS),
+            teams: None,
+            setup: Setup::default(),
+        }
+    }
+}
+
+/// How often a player made each kind of move.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActionCounts {
+    pub income: usize,
+    pub foreign_aid: usize,
+    pub tax: usize,
+    pub coup: usize,
+    pub assassinate: usize,
+    pub steal: usize,
+    pub exchange: usize,
+    pub block: usize,
+    pub challenge: usize,
+    pub pass: usize,
+    pub convert: usize,
+    pub embezzle: usize,
+    pub examine: usize,
+    pub give: usize,
+    pub redistribute: usize,
+    pub speculate: usize,
+    pub report: usize,
+}
+
+impl ActionCounts {
+    pub fn add(&mut self, action: &Action) {
+        match action {
+            Action::Income => self.income += 1,
+            Action::ForeignAid => self.foreign_aid += 1,
+            Action::Tax => self.tax += 1,
+            Action::Coup(_) => self.coup += 1,
+            Action::Assassinate(_) => self.assassinate += 1,
+            Action::Steal(_, _) => self.steal += 1,
+            Action::Exchange => self.exchange += 1,
+            Action::Block(_, _, _) => self.block += 1,
+            Action::Challenge(_, _) => self.challenge += 1,
+            Action::Pass => self.pass += 1,
+            Action::Convert(_) => self.convert += 1,
+            Action::Embezzle => self.embezzle += 1,
+            Action::Examine(_) => self.examine += 1,
+            Action::Give(_, _) => self.give += 1,
+            Action::Redistribute => self.redistribute += 1,
+            Action::Speculate => self.speculate += 1,
+            Action::Report => self.report += 1,
+            // Decided while an examination resolves, never played on its own.
+            Action::ForceExchange(_) => {},
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.income + self.foreign_aid + self.tax + self.coup + self.assassinate + self.steal + self.exchange + self.block + self.challenge + self.pass + self.convert + self.embezzle + self.examine + self.give
+            + self.redistribute + self.speculate + self.report
+    }
+}
+
+/// What happened in one simulated game. Seats are the order after setup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSummary {
+    pub game: usize,
+    pub seed: u64,
+    /// Player names by seat; each is `<spec>#<index in SimulationConfig::agents>`.
+    pub names: Vec<String>,
+    /// Team by seat, in team games.
+    pub teams: Option<Vec<usize>>,
+    /// Winner, placements and eliminations.
+    pub result: GameResult,
+    pub actions: Vec<ActionCounts>,
+}
+
+/// Plays one game of `config` with fresh agents, every random choice drawn from `seed`.
+pub fn simulate_game(config: &SimulationConfig, game: usize, seed: u64) -> Result<GameSummary, String> {
+    config.rules.check_players(config.agents.len())?;
+    let mut players: Vec<Box<dyn Player>> = Vec::new();
+    for (i, agent) in config.agents.iter().enumerate() {
+        players.push(agent.build(&format!("{}#{}", agent, i), seed.wrapping_mul(31).wrapping_add(i as u64))?);
+    }
+    let mut state = GameState::new(players);
+    if let Some(teams) = &config.teams {
+        teams.validate(config.agents.len())?;
+        state = state.with_teams(teams.clone());
+    }
+    state.verbose = false;
+    state.rng = StdRng::seed_from_u64(seed);
+    state.set_rules(config.rules.clone());
+    game::start_game_with(&mut state, &config.setup)?;
+    if config.reformation {
+        state.enable_reformation();
+    }
+    state.run();
+
+    let mut actions = vec![ActionCounts::default(); state.players.len()];
+    for event in state.events.iter() {
+        match event {
+            Event::Played { player, action } => actions[*player].add(action),
+            Event::Passed { player, .. } => actions[*player].add(&Action::Pass),
+            _ => {},
+        }
+    }
+
+    Ok(GameSummary {
+        game,
+        seed,
+        names: state.players.iter().map(|p| p.name()).collect(),
+        teams: state.teams.as_ref().map(|t| t.team.clone()),
+        result: state.result().expect("the game ran to the end"),
+        actions,
+    })
+}
+
+/// Runs `config.games` games on `config.threads` workers. Game `g` always uses seed
+/// `config.seed + g`, so the summaries, returned in game order, do not depend on the
+/// number of threads.
+pub fn simulate(config: &SimulationConfig) -> Result<Vec<GameSummary>, String> {
+    run_games(config.games, config.threads, config.seed, |g, seed| simulate_game(config, g, seed))
+}
+
+/// Plays `games` games on `threads` workers, which take the next unplayed game as they
+/// finish. `play` gets the game number `g` and its seed `seed + g`; the outputs come
+/// back in game order, or the error of the first game that failed.
+pub fn run_games<T, F>(games: usize, threads: usize, seed: u64, play: F) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(usize, u64) -> Result<T, String> + Sync,
+{
+    let threads = threads.max(1);
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<T, String>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| {
+            scope.spawn(|| {
+                let mut outputs = Vec::new();
+                loop {
+                    let g = next.fetch_add(1, Ordering::Relaxed);
+                    if g >= games {
+                        break outputs;
+                    }
+                    outputs.push((g, play(g, seed.wrapping_add(g as u64))));
+                }
+            })
+        }).collect();
+        workers.into_iter().flat_map(|w| w.join().expect("simulation worker panicked")).collect()
+    });
+    results.sort_by_key(|(g, _)| *g);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Throughput of one [`simulate`] run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Benchmark {
+    pub games: usize,
+    pub turns: usize,
+    pub elapsed: Duration,
+}
+
+impl Benchmark {
+    pub fn games_per_second(&self) -> f64 {
+        self.games as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn turns_per_second(&self) -> f64 {
+        self.turns as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+pub fn benchmark(config: &SimulationConfig) -> Result<Benchmark, String> {
+    let started = Instant::now();
+    let summaries = simulate(config)?;
+    Ok(Benchmark {
+        games: summaries.len(),
+        turns: summaries.iter().map(|s| s.result.turns).sum(),
+        elapsed: started.elapsed(),
+    })
+}
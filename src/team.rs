@@ -0,0 +1,61 @@
+/// Team play: the team of every player and how teammates may treat each other. The
+/// game ends when only one team has players left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Teams {
+    /// Team number by seat.
+    pub team: Vec<usize>,
+    /// Teammates may Coup, Assassinate, Steal from and block each other.
+    pub friendly_fire: bool,
+    /// Teammates may Give each other coins.
+    pub giving: bool,
+}
+
+impl Teams {
+    pub fn new(team: Vec<usize>) -> Teams {
+        Teams { team, friendly_fire: false, giving: false }
+    }
+
+    /// `num_players` split into `num_teams` teams taking turns around the table,
+    /// e.g. 0 1 0 1 for 2v2.
+    pub fn alternating(num_players: usize, num_teams: usize) -> Teams {
+        Teams::new((0..num_players).map(|i| i % num_teams.max(1)).collect())
+    }
+
+    pub fn with_friendly_fire(mut self) -> Self {
+        self.friendly_fire = true;
+        self
+    }
+
+    pub fn with_giving(mut self) -> Self {
+        self.giving = true;
+        self
+    }
+
+    pub fn same_team(&self, a: usize, b: usize) -> bool {
+        self.team[a] == self.team[b]
+    }
+
+    pub fn num_teams(&self) -> usize {
+        self.team.iter().max().map_or(0, |t| t + 1)
+    }
+
+    /// Seats of the players in `team`.
+    pub fn members(&self, team: usize) -> Vec<usize> {
+        (0..self.team.len()).filter(|&i| self.team[i] == team).collect()
+    }
+
+    /// Checks that the teams fit a table of `num_players`, with at least two teams and
+    /// no team left empty.
+    pub fn validate(&self, num_players: usize) -> Result<(), String> {
+        if self.team.len() != num_players {
+            return Err(format!("{} team assignments for {} players", self.team.len(), num_players));
+        }
+        if self.num_teams() < 2 {
+            return Err("Team play needs at least two teams".to_string());
+        }
+        if let Some(empty) = (0..self.num_teams()).find(|&t| self.members(t).is_empty()) {
+            return Err(format!("Team {} has no players", empty));
+        }
+        Ok(())
+    }
+}
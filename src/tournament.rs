@@ -0,0 +1,335 @@
+use std::fmt::Write;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::config::STALEMATE_ROUNDS;
+use crate::player::spec::AgentSpec;
+use crate::ruleset::Ruleset;
+use crate::series::{Match, MatchConfig};
+use crate::simulation;
+
+const ELO_START: f64 = 1500.0;
+const ELO_K: f64 = 32.0;
+const MU_START: f64 = 25.0;
+const SIGMA_START: f64 = MU_START / 3.0;
+const BETA: f64 = SIGMA_START / 2.0;
+const KAPPA: f64 = 0.0001;
+
+/// How entrants are put together at tables.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pairing {
+    /// Every combination of `table_size` entrants plays once.
+    RoundRobin,
+    /// Each round seats entrants with similar results together. Entrants left over from
+    /// full tables sit the round out, taking turns.
+    Swiss { rounds: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct TournamentConfig {
+    pub agents: Vec<AgentSpec>,
+    pub table_size: usize,
+    pub pairing: Pairing,
+    /// Every table plays this many games from each rotation of its seating.
+    pub games_per_seating: usize,
+    /// Every game is a best-of-N [`Match`] whose winner counts as the game's winner.
+    pub best_of: usize,
+    /// Characters in play, their powers and the coin amounts of the actions.
+    pub rules: Ruleset,
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        TournamentConfig {
+            agents: vec![AgentSpec::Random; 3],
+            table_size: 3,
+            pairing: Pairing::RoundRobin,
+            games_per_seating: 10,
+            best_of: 1,
+            rules: Ruleset::classic().with_stalemate_rounds(STALEMATE_ROUNDS),
+            seed: 0,
+            threads: 1,
+        }
+    }
+}
+
+/// Results and ratings of one entrant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub agent: AgentSpec,
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    /// Sum over games of 1/table size, i.e. the wins expected from a player no better
+    /// than its opponents.
+    pub expected_wins: f64,
+    pub elo: f64,
+    /// Bayesian skill estimate (Weng-Lin, in the style of TrueSkill).
+    pub mu: f64,
+    pub sigma: f64,
+}
+
+impl Standing {
+    fn new(agent: AgentSpec, name: String) -> Standing {
+        Standing { agent, name, games: 0, wins: 0, expected_wins: 0.0, elo: ELO_START, mu: MU_START, sigma: SIGMA_START }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { self.wins as f64 / self.games as f64 }
+    }
+
+    /// 95% Wilson score interval for the win rate.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        if self.games == 0 {
+            return (0.0, 1.0);
+        }
+        let z = 1.96;
+        let n = self.games as f64;
+        let p = self.win_rate();
+        let centre = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+        let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / (1.0 + z * z / n);
+        ((centre - half).max(0.0), (centre + half).min(1.0))
+    }
+
+    /// Skill the entrant is very likely to have at least: mu - 3 sigma.
+    pub fn conservative_rating(&self) -> f64 {
+        self.mu - 3.0 * self.sigma
+    }
+}
+
+/// One game to play: the entrants by seat and the game's seed.
+struct Scheduled {
+    seating: Vec<usize>,
+    seed: u64,
+}
+
+/// Plays the tournament and returns the standings, best conservative rating first.
+/// Seeds are fixed per game, so results do not depend on the number of threads.
+pub fn run(config: &TournamentConfig) -> Result<Vec<Standing>, String> {
+    let k = config.table_size;
+    if !(2..=6).contains(&k) {
+        return Err("Tables must seat 2 to 6 players".to_string());
+    }
+    if config.agents.len() < k {
+        return Err(format!("{} agents cannot fill a table of {}", config.agents.len(), k));
+    }
+    let mut standings: Vec<Standing> = config.agents.iter().enumerate()
+        .map(|(i, agent)| Standing::new(agent.clone(), format!("{}#{}", agent, i)))
+        .collect();
+
+    let mut next_game = 0;
+    match config.pairing {
+        Pairing::RoundRobin => {
+            let tables = combinations(config.agents.len(), k);
+            let schedule = schedule_tables(&tables, config, &mut next_game);
+            play_and_rate(&schedule, config, &mut standings)?;
+        },
+        Pairing::Swiss { rounds } => {
+            let mut order: Vec<usize> = (0..config.agents.len()).collect();
+            order.shuffle(&mut StdRng::seed_from_u64(config.seed));
+            let mut byes = vec![0; order.len()];
+            for _ in 0..rounds {
+                // Stable sort keeps the previous order among equal scores.
+                order.sort_by(|&a, &b| standings[b].wins.cmp(&standings[a].wins));
+                // The bye goes to whoever has had the fewest, lowest placed first.
+                let mut sitting_out: Vec<usize> = order.iter().rev().copied().collect();
+                sitting_out.sort_by_key(|&e| byes[e]);
+                sitting_out.truncate(order.len() % k);
+                for &e in &sitting_out {
+                    byes[e] += 1;
+                }
+                let playing: Vec<usize> = order.iter().copied().filter(|e| !sitting_out.contains(e)).collect();
+                let tables: Vec<Vec<usize>> = playing.chunks_exact(k).map(|t| t.to_vec()).collect();
+                let schedule = schedule_tables(&tables, config, &mut next_game);
+                play_and_rate(&schedule, config, &mut standings)?;
+            }
+        },
+    }
+
+    standings.sort_by(|a, b| b.conservative_rating().total_cmp(&a.conservative_rating()));
+    Ok(standings)
+}
+
+fn schedule_tables(tables: &[Vec<usize>], config: &TournamentConfig, next_game: &mut u64) -> Vec<Scheduled> {
+    let mut schedule = Vec::new();
+    for table in tables {
+        for _ in 0..config.games_per_seating {
+            for rotation in 0..table.len() {
+                let mut seating = table.clone();
+                seating.rotate_left(rotation);
+                schedule.push(Scheduled { seating, seed: config.seed.wrapping_mul(1_000_003).wrapping_add(*next_game) });
+                *next_game += 1;
+            }
+        }
+    }
+    schedule
+}
+
+/// Plays `schedule` with [`simulation::run_games`], then applies the results in
+/// schedule order. Every game keeps the seed it was scheduled with.
+fn play_and_rate(schedule: &[Scheduled], config: &TournamentConfig, standings: &mut [Standing]) -> Result<(), String> {
+    let names: Vec<String> = standings.iter().map(|s| s.name.clone()).collect();
+    let winners = simulation::run_games(schedule.len(), config.threads, 0, |g, _| play_game(&schedule[g], config, &names))?;
+    for (scheduled, winner) in schedule.iter().zip(winners) {
+        rate(standings, &scheduled.seating, winner);
+    }
+    Ok(())
+}
+
+/// Plays one game, or one match of `config.best_of` games, with the seating as
+/// scheduled and returns the winning entrant, or None for a draw.
+fn play_game(scheduled: &Scheduled, config: &TournamentConfig, names: &[String]) -> Result<Option<usize>, String> {
+    let agents: Vec<AgentSpec> = scheduled.seating.iter().map(|&e| config.agents[e].clone()).collect();
+    let names: Vec<String> = scheduled.seating.iter().map(|&e| names[e].clone()).collect();
+    let match_config = MatchConfig { games: config.best_of.max(1), seed: scheduled.seed, rules: config.rules.clone(), ..MatchConfig::default() };
+    let mut series = Match::new(agents, match_config)?.with_names(&names);
+    series.play()?;
+    Ok(series.winner().map(|i| scheduled.seating[i]))
+}
+
+/// Updates records, Elo and the Bayesian ratings for a game where `winner` beat the
+/// rest of `table`, who count as tied with each other. A draw ties everyone.
+fn rate(standings: &mut [Standing], table: &[usize], winner: Option<usize>) {
+    let k = table.len();
+    let score = |a: usize, b: usize| if Some(a) == winner { 1.0 } else if Some(b) == winner { 0.0 } else { 0.5 };
+
+    let elo: Vec<f64> = table.iter().map(|&i| standings[i].elo).collect();
+    let mu: Vec<f64> = table.iter().map(|&i| standings[i].mu).collect();
+    let sigma: Vec<f64> = table.iter().map(|&i| standings[i].sigma).collect();
+
+    for (a, &i) in table.iter().enumerate() {
+        let mut elo_change = 0.0;
+        let mut omega = 0.0;
+        let mut delta = 0.0;
+        for (b, &j) in table.iter().enumerate() {
+            if a == b {
+                continue;
+            }
+            let s = score(i, j);
+            let expected = 1.0 / (1.0 + 10f64.powf((elo[b] - elo[a]) / 400.0));
+            elo_change += ELO_K / (k - 1) as f64 * (s - expected);
+
+            // Weng-Lin Bradley-Terry update with every opponent.
+            let c = (sigma[a] * sigma[a] + sigma[b] * sigma[b] + 2.0 * BETA * BETA).sqrt();
+            let p = 1.0 / (1.0 + ((mu[b] - mu[a]) / c).exp());
+            let gamma = sigma[a] / c;
+            omega += sigma[a] * sigma[a] / c * (s - p);
+            delta += gamma * sigma[a] * sigma[a] / (c * c) * p * (1.0 - p);
+        }
+        let standing = &mut standings[i];
+        standing.games += 1;
+        standing.expected_wins += 1.0 / k as f64;
+        if Some(i) == winner {
+            standing.wins += 1;
+        }
+        standing.elo += elo_change;
+        standing.mu += omega;
+        standing.sigma *= (1.0 - delta).max(KAPPA).sqrt();
+    }
+}
+
+/// All ways to choose `k` of `n` entrants, in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    fn extend(from: usize, n: usize, k: usize, current: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in from..n {
+            current.push(i);
+            extend(i + 1, n, k, current, out);
+            current.pop();
+        }
+    }
+    let mut out = Vec::new();
+    extend(0, n, k, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Standings as a plain-text table.
+pub fn report(standings: &[Standing]) -> String {
+    let width = standings.iter().map(|s| s.name.len()).max().unwrap_or(5).max(5);
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>4}  {:<width$}  {:>6}  {:>6}  {:>7}  {:>15}  {:>7}  {:>6}  {:>13}", "rank", "agent", "games", "wins", "win%", "95% CI", "vs exp", "elo", "rating", width = width);
+    for (rank, s) in standings.iter().enumerate() {
+        let (low, high) = s.confidence_interval();
+        let _ = writeln!(
+            out,
+            "{:>4}  {:<width$}  {:>6}  {:>6}  {:>6.1}%  {:>6.1}%-{:>6.1}%  {:>+7.1}  {:>6.0}  {:>5.1} ± {:>4.1}",
+            rank + 1, s.name, s.games, s.wins, 100.0 * s.win_rate(), 100.0 * low, 100.0 * high,
+            s.wins as f64 - s.expected_wins, s.elo, s.mu, s.sigma,
+            width = width,
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combinations, rate, run, Pairing, Standing, TournamentConfig, ELO_START, MU_START};
+    use crate::player::spec::AgentSpec;
+
+    fn standings(n: usize) -> Vec<Standing> {
+        (0..n).map(|i| Standing::new(AgentSpec::Random, format!("random#{}", i))).collect()
+    }
+
+    #[test]
+    fn a_win_moves_the_ratings_apart_and_a_draw_keeps_them_level() {
+        let mut won = standings(2);
+        rate(&mut won, &[0, 1], Some(0));
+        assert!(won[0].elo > ELO_START && won[1].elo < ELO_START);
+        assert!(won[0].mu > MU_START && won[1].mu < MU_START);
+        assert_eq!((won[0].wins, won[1].wins, won[1].games), (1, 0, 1));
+
+        let mut drawn = standings(3);
+        rate(&mut drawn, &[0, 1, 2], None);
+        for standing in &drawn {
+            assert_eq!((standing.elo, standing.mu, standing.wins, standing.games), (ELO_START, MU_START, 0, 1));
+        }
+    }
+
+    #[test]
+    fn combinations_are_listed_in_order() {
+        assert_eq!(combinations(4, 2), vec![vec![0, 1], vec![0, 2], vec![0, 3], vec![1, 2], vec![1, 3], vec![2, 3]]);
+        assert_eq!(combinations(3, 3), vec![vec![0, 1, 2]]);
+        assert!(combinations(2, 3).is_empty());
+    }
+
+    #[test]
+    fn confidence_interval_stays_inside_the_unit_range() {
+        let mut standing = Standing::new(AgentSpec::Random, "random".to_string());
+        standing.games = 20;
+        let (low, high) = standing.confidence_interval();
+        assert!(low.abs() < 1e-12 && high > 0.0 && high < 0.5);
+
+        standing.wins = 20;
+        let (low, high) = standing.confidence_interval();
+        assert!(low > 0.5 && low < 1.0 && (high - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn standings_do_not_depend_on_the_threads() {
+        let config = TournamentConfig { agents: vec![AgentSpec::Random; 4], games_per_seating: 2, seed: 7, ..TournamentConfig::default() };
+        let one = run(&TournamentConfig { threads: 1, ..config.clone() }).unwrap();
+        let four = run(&TournamentConfig { threads: 4, ..config }).unwrap();
+        assert_eq!(one, four);
+    }
+
+    #[test]
+    fn swiss_byes_rotate_through_the_entrants() {
+        let config = TournamentConfig {
+            agents: vec![AgentSpec::Random; 4],
+            pairing: Pairing::Swiss { rounds: 4 },
+            games_per_seating: 1,
+            ..TournamentConfig::default()
+        };
+        //one table of three a round, so everyone sits out exactly one of the four rounds
+        for standing in run(&config).unwrap() {
+            assert_eq!(standing.games, 3 * 3);
+        }
+    }
+}